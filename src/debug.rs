@@ -0,0 +1,298 @@
+use bevy::{core::FrameCount, prelude::*};
+use bevy_rapier2d::{prelude::*, render::DebugRenderContext};
+use leafwing_input_manager::prelude::ActionState;
+
+pub mod editor;
+pub mod tuning;
+
+use crate::player::{
+    input::InputAction,
+    movement::CharacterController,
+    state_machine::history::{record_state_changes, PlayerState, StateHistory},
+    Player, PlayerStartupSet,
+};
+
+/// Runtime debug overlays, the `F9` tuning panel and the `F10` level editor. Only compiled with
+/// the `debug` feature, so release builds can leave the whole toolkit out with
+/// `--no-default-features`
+pub(super) struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            RapierDebugRenderPlugin::default().disabled(),
+            tuning::TuningPanelPlugin,
+            editor::LevelEditorPlugin,
+        ))
+        .init_resource::<DebugSettings>()
+        .add_systems(
+            Startup,
+            spawn_state_label.in_set(PlayerStartupSet::PostPlayer),
+        )
+        .add_systems(
+            Update,
+            (
+                debug_hotkeys,
+                sync_collider_render.run_if(resource_changed::<DebugSettings>()),
+                draw_velocity.run_if(overlay_enabled(DebugOverlay::Velocity)),
+                update_state_label,
+                draw_timer_bars.run_if(overlay_enabled(DebugOverlay::TimerBars)),
+            )
+                .chain(),
+        )
+        .add_systems(
+            PostUpdate,
+            report_eaten_jumps
+                .after(record_state_changes)
+                .run_if(overlay_enabled(DebugOverlay::TimerBars)),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugOverlay {
+    Colliders,
+    SurfaceSensors,
+    Velocity,
+    StateLabels,
+    /// Coyote time and jump buffer, also reports jumps that were eaten
+    TimerBars,
+}
+
+impl DebugOverlay {
+    pub const ALL: [DebugOverlay; 5] = [
+        DebugOverlay::Colliders,
+        DebugOverlay::SurfaceSensors,
+        DebugOverlay::Velocity,
+        DebugOverlay::StateLabels,
+        DebugOverlay::TimerBars,
+    ];
+
+    /// Key that toggles the overlay on its own
+    pub fn key(&self) -> KeyCode {
+        match self {
+            DebugOverlay::Colliders => KeyCode::F4,
+            DebugOverlay::SurfaceSensors => KeyCode::F5,
+            DebugOverlay::Velocity => KeyCode::F6,
+            DebugOverlay::StateLabels => KeyCode::F7,
+            DebugOverlay::TimerBars => KeyCode::F8,
+        }
+    }
+}
+
+/// Which debug overlays are shown. `F3` cycles through showing nothing, each overlay alone and
+/// every overlay, and each overlay's [`DebugOverlay::key`] toggles it on its own
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct DebugSettings {
+    pub colliders: bool,
+    pub surface_sensors: bool,
+    pub velocity: bool,
+    pub state_labels: bool,
+    pub timer_bars: bool,
+
+    /// Position in the `F3` cycle
+    cycle: usize,
+}
+
+impl DebugSettings {
+    pub const CYCLE_KEY: KeyCode = KeyCode::F3;
+
+    fn flag(&mut self, overlay: DebugOverlay) -> &mut bool {
+        match overlay {
+            DebugOverlay::Colliders => &mut self.colliders,
+            DebugOverlay::SurfaceSensors => &mut self.surface_sensors,
+            DebugOverlay::Velocity => &mut self.velocity,
+            DebugOverlay::StateLabels => &mut self.state_labels,
+            DebugOverlay::TimerBars => &mut self.timer_bars,
+        }
+    }
+
+    pub fn enabled(&self, overlay: DebugOverlay) -> bool {
+        match overlay {
+            DebugOverlay::Colliders => self.colliders,
+            DebugOverlay::SurfaceSensors => self.surface_sensors,
+            DebugOverlay::Velocity => self.velocity,
+            DebugOverlay::StateLabels => self.state_labels,
+            DebugOverlay::TimerBars => self.timer_bars,
+        }
+    }
+
+    pub fn set(&mut self, overlay: DebugOverlay, enabled: bool) {
+        *self.flag(overlay) = enabled;
+    }
+
+    pub fn toggle(&mut self, overlay: DebugOverlay) {
+        let flag = self.flag(overlay);
+        *flag = !*flag;
+    }
+
+    /// Steps to the next preset: nothing, then every overlay alone, then all of them
+    pub fn cycle(&mut self) {
+        self.cycle = (self.cycle + 1) % (DebugOverlay::ALL.len() + 2);
+
+        for (i, overlay) in DebugOverlay::ALL.into_iter().enumerate() {
+            let enabled = self.cycle == i + 1 || self.cycle == DebugOverlay::ALL.len() + 1;
+            self.set(overlay, enabled);
+        }
+    }
+}
+
+/// Run condition for systems that draw `overlay`
+pub fn overlay_enabled(overlay: DebugOverlay) -> impl Fn(Res<DebugSettings>) -> bool + Clone {
+    move |settings: Res<DebugSettings>| settings.enabled(overlay)
+}
+
+fn debug_hotkeys(mut settings: ResMut<DebugSettings>, keyboard: Res<Input<KeyCode>>) {
+    if keyboard.just_pressed(DebugSettings::CYCLE_KEY) {
+        settings.cycle();
+    }
+
+    for overlay in DebugOverlay::ALL {
+        if keyboard.just_pressed(overlay.key()) {
+            settings.toggle(overlay);
+        }
+    }
+}
+
+fn sync_collider_render(settings: Res<DebugSettings>, mut render: ResMut<DebugRenderContext>) {
+    render.enabled = settings.colliders;
+}
+
+fn draw_velocity(
+    player_query: Query<(&GlobalTransform, &Velocity), With<Player>>,
+    mut gizmos: Gizmos,
+) {
+    for (transform, vel) in player_query.iter() {
+        let position = transform.translation().truncate();
+        // Scaled down so a second of movement doesn't leave the screen
+        gizmos.line_2d(position, position + vel.linvel * 0.2f32, Color::ORANGE);
+    }
+}
+
+#[derive(Component)]
+struct StateLabel;
+
+fn spawn_state_label(mut cmd: Commands, player_query: Query<Entity, With<Player>>) {
+    let label = cmd
+        .spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16f32,
+                        color: Color::WHITE,
+                        ..Default::default()
+                    },
+                ),
+                transform: Transform::from_xyz(0f32, 45f32, 10f32),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            StateLabel,
+            Name::from("State label"),
+        ))
+        .id();
+
+    cmd.entity(player_query.single()).add_child(label);
+}
+
+fn update_state_label(
+    settings: Res<DebugSettings>,
+    player_query: Query<(&StateHistory, &Children), With<Player>>,
+    mut label_query: Query<(&mut Text, &mut Visibility), With<StateLabel>>,
+) {
+    for (history, children) in player_query.iter() {
+        let mut labels = label_query.iter_many_mut(children);
+        while let Some((mut text, mut visibility)) = labels.fetch_next() {
+            *visibility = if settings.state_labels {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+
+            text.sections[0].value = match history.current() {
+                Some(PlayerState::Grounded(state)) => format!("Grounded ({state:?})"),
+                Some(PlayerState::Jumping(multi)) => format!("Jumping ({multi:.2})"),
+                Some(state) => format!("{state:?}"),
+                None => String::new(),
+            };
+        }
+    }
+}
+
+fn draw_timer_bars(
+    player_query: Query<(&GlobalTransform, &CharacterController), With<Player>>,
+    mut gizmos: Gizmos,
+) {
+    const WIDTH: f32 = 40f32;
+
+    for (transform, controller) in player_query.iter() {
+        let origin = transform.translation().truncate() + Vec2::new(-WIDTH / 2f32, -35f32);
+
+        for (i, (timer, color)) in [
+            (&controller.coyote_timer, Color::LIME_GREEN),
+            (&controller.jump_buffer_timer, Color::CYAN),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let start = origin - Vec2::Y * 4f32 * i as f32;
+            gizmos.line_2d(start, start + Vec2::X * WIDTH, Color::DARK_GRAY);
+            gizmos.line_2d(start, start + Vec2::X * WIDTH * timer.percent_left(), color);
+        }
+    }
+}
+
+/// Prints the recent transitions whenever a buffered jump expires without the player jumping
+fn report_eaten_jumps(
+    player_query: Query<
+        (
+            &StateHistory,
+            &CharacterController,
+            &ActionState<InputAction>,
+        ),
+        With<Player>,
+    >,
+    mut pressed_at: Local<Option<u32>>,
+    frames: Res<FrameCount>,
+) {
+    let Ok((history, controller, input)) = player_query.get_single() else {
+        return;
+    };
+
+    if input.just_pressed(InputAction::Jump) {
+        *pressed_at = Some(frames.0);
+    }
+
+    let Some(pressed) = *pressed_at else {
+        return;
+    };
+
+    let jumped = history
+        .entries()
+        .rev()
+        .take_while(|entry| entry.tick >= pressed)
+        .any(|entry| matches!(entry.to, PlayerState::Jumping(_)));
+
+    if jumped {
+        *pressed_at = None;
+        return;
+    }
+
+    if !controller.jump_buffer_timer.finished() {
+        return;
+    }
+
+    *pressed_at = None;
+    println!(
+        "Jump pressed on frame {pressed} was eaten. State: {:?}, coyote time left: {:?}",
+        history.current(),
+        controller.coyote_timer.remaining(),
+    );
+    for entry in history.entries().rev().take(5) {
+        println!(
+            "    frame {} ({:.3}s): {:?} -> {:?}",
+            entry.tick, entry.time, entry.from, entry.to
+        );
+    }
+}
@@ -0,0 +1,621 @@
+use bevy::{
+    ecs::system::SystemParam, input::mouse::MouseMotion, prelude::*, window::PrimaryWindow,
+};
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+use std::path::Path;
+
+use crate::{
+    level::{
+        ldtk::{self, LdtkEntities},
+        spawn_level, GroundData, LevelData, LevelEntity, LevelSequence, LevelState,
+    },
+    menu::{AppState, PhysicsPause},
+    player::{camera::CameraFollow, input::InputAction, Player, PlayerStartupSet},
+};
+
+/// Distance from a corner of the selected ground that grabs it for resizing
+const HANDLE_SIZE: f32 = 8f32;
+/// Right clicks this close to a checkpoint remove it
+const CHECKPOINT_PICK_DISTANCE: f32 = 30f32;
+const PAN_SPEED: f32 = 800f32;
+
+/// Level editor toggled with `F10`. The level is edited as [`LevelData`] and respawned with
+/// [`spawn_level`] on every change, so it plays exactly like the saved file
+pub(super) struct LevelEditorPlugin;
+
+impl Plugin for LevelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelEditor>()
+            .add_systems(
+                Startup,
+                spawn_editor_label.in_set(PlayerStartupSet::PostPlayer),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_editor,
+                    (select_tool, pan_camera, edit_level, save_level, draw_editor)
+                        .run_if(|editor: Res<LevelEditor>| editor.enabled),
+                    update_editor_label.run_if(resource_changed::<LevelEditor>()),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(LevelState::Playing)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditorTool {
+    /// Click and drag to draw a new ground
+    #[default]
+    Ground,
+    /// Click a ground to select it, drag it to move it and drag its corners to resize it
+    Select,
+    Spawn,
+    /// Left click adds a checkpoint, right click removes one
+    Checkpoint,
+}
+
+impl EditorTool {
+    pub const ALL: [EditorTool; 4] = [
+        EditorTool::Ground,
+        EditorTool::Select,
+        EditorTool::Spawn,
+        EditorTool::Checkpoint,
+    ];
+
+    pub fn key(&self) -> KeyCode {
+        match self {
+            EditorTool::Ground => KeyCode::Key1,
+            EditorTool::Select => KeyCode::Key2,
+            EditorTool::Spawn => KeyCode::Key3,
+            EditorTool::Checkpoint => KeyCode::Key4,
+        }
+    }
+}
+
+/// A level file and its unsaved edits
+#[derive(Clone, Debug)]
+pub struct EditedLevel {
+    pub path: String,
+    pub data: LevelData,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Drag {
+    /// Drawing a new ground from this corner
+    Create(Vec2),
+    /// Moving the selected ground, held at this offset from its center
+    Move(Vec2),
+    /// Resizing the selected ground around this corner
+    Resize(Vec2),
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct LevelEditor {
+    pub enabled: bool,
+    pub tool: EditorTool,
+    /// Size of the grid everything snaps to, toggled with `G`
+    pub grid: Option<f32>,
+    pub level: Option<EditedLevel>,
+    pub selected: Option<usize>,
+
+    drag: Option<Drag>,
+    /// Taken off the camera while editing, so it doesn't follow the player
+    camera_follow: Option<CameraFollow>,
+}
+
+impl LevelEditor {
+    pub const TOGGLE_KEY: KeyCode = KeyCode::F10;
+    /// Leaves the editor and drops the player at the cursor
+    pub const PLAY_TEST_KEY: KeyCode = KeyCode::Return;
+    pub const GRID_SIZE: f32 = 25f32;
+}
+
+impl Default for LevelEditor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tool: EditorTool::default(),
+            grid: Some(Self::GRID_SIZE),
+            level: None,
+            selected: None,
+            drag: None,
+            camera_follow: None,
+        }
+    }
+}
+
+fn snap(position: Vec2, grid: Option<f32>) -> Vec2 {
+    match grid {
+        Some(size) => (position / size).round() * size,
+        None => position,
+    }
+}
+
+#[derive(SystemParam)]
+struct EditorCursor<'w, 's> {
+    window_query: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
+
+impl EditorCursor<'_, '_> {
+    fn world_position(&self) -> Option<Vec2> {
+        let window = self.window_query.get_single().ok()?;
+        let (camera, transform) = self.camera_query.get_single().ok()?;
+        camera.viewport_to_world_2d(transform, window.cursor_position()?)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn toggle_editor(
+    mut cmd: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    mut editor: ResMut<LevelEditor>,
+    sequence: Res<LevelSequence>,
+    ldtk_entities: Res<LdtkEntities>,
+    mut physics_pause: ResMut<PhysicsPause>,
+    mut player_input: ResMut<ToggleActions<InputAction>>,
+    cursor: EditorCursor,
+    camera_query: Query<(Entity, Option<&CameraFollow>), With<Camera>>,
+    mut player_query: Query<(&mut Transform, &mut Velocity), With<Player>>,
+) {
+    let play_test = editor.enabled && keyboard.just_pressed(LevelEditor::PLAY_TEST_KEY);
+    if !keyboard.just_pressed(LevelEditor::TOGGLE_KEY) && !play_test {
+        return;
+    }
+
+    editor.enabled = !editor.enabled;
+    editor.drag = None;
+    physics_pause.editor = editor.enabled;
+    // The arrow keys pan the camera instead of running
+    player_input.enabled = !editor.enabled;
+
+    for (camera, follow) in camera_query.iter() {
+        if editor.enabled {
+            editor.camera_follow = follow.cloned();
+            cmd.entity(camera).remove::<CameraFollow>();
+        } else if let Some(follow) = editor.camera_follow.take() {
+            cmd.entity(camera).insert(follow);
+        }
+    }
+
+    if editor.enabled {
+        let path = sequence.current_path();
+        if editor.level.as_ref().map(|level| level.path.as_str()) != Some(path) {
+            editor.level = match LevelData::load_with_entities(path, &ldtk_entities) {
+                Ok(data) => Some(EditedLevel {
+                    path: path.to_string(),
+                    data,
+                }),
+                Err(err) => {
+                    println!("Could not open level {path} in the editor. {err}");
+                    None
+                }
+            };
+            editor.selected = None;
+        }
+    }
+
+    let start = if play_test {
+        cursor.world_position()
+    } else {
+        None
+    };
+    for (mut transform, mut vel) in player_query.iter_mut() {
+        vel.linvel = Vec2::ZERO;
+        if let Some(start) = start {
+            transform.translation = start.extend(transform.translation.z);
+        }
+    }
+}
+
+fn select_tool(keyboard: Res<Input<KeyCode>>, mut editor: ResMut<LevelEditor>) {
+    for tool in EditorTool::ALL {
+        if keyboard.just_pressed(tool.key()) && editor.tool != tool {
+            editor.tool = tool;
+            editor.drag = None;
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::G) {
+        editor.grid = match editor.grid {
+            Some(_) => None,
+            None => Some(LevelEditor::GRID_SIZE),
+        };
+    }
+}
+
+/// Arrow keys or dragging with the middle mouse button move the camera
+fn pan_camera(
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+    time: Res<Time>,
+) {
+    let axis = |negative, positive| {
+        keyboard.pressed(positive) as i32 as f32 - keyboard.pressed(negative) as i32 as f32
+    };
+    let mut offset = Vec2::new(
+        axis(KeyCode::Left, KeyCode::Right),
+        axis(KeyCode::Down, KeyCode::Up),
+    ) * PAN_SPEED
+        * time.delta_seconds();
+
+    for event in motion.iter() {
+        if mouse.pressed(MouseButton::Middle) {
+            offset += Vec2::new(-event.delta.x, event.delta.y);
+        }
+    }
+
+    for mut transform in camera_query.iter_mut() {
+        transform.translation += offset.extend(0f32);
+    }
+}
+
+fn rect_between(a: Vec2, b: Vec2) -> Option<(Vec2, Vec2)> {
+    let size = (a - b).abs();
+    (size.x > 0f32 && size.y > 0f32).then_some(((a + b) / 2f32, size))
+}
+
+/// The corner of `ground` under `position`, if any
+fn grabbed_corner(ground: &GroundData, position: Vec2) -> Option<Vec2> {
+    let half = ground.size / 2f32;
+    [
+        Vec2::new(-1f32, -1f32),
+        Vec2::new(1f32, -1f32),
+        Vec2::new(-1f32, 1f32),
+        Vec2::new(1f32, 1f32),
+    ]
+    .into_iter()
+    .map(|corner| ground.position + corner * half)
+    .find(|corner| corner.distance(position) <= HANDLE_SIZE)
+}
+
+fn contains(ground: &GroundData, position: Vec2) -> bool {
+    ((position - ground.position).abs() * 2f32)
+        .cmple(ground.size)
+        .all()
+}
+
+fn unused_ground_name(level: &LevelData) -> String {
+    (level.grounds.len() + 1..)
+        .map(|i| format!("Ground {i}"))
+        .find(|name| level.grounds.iter().all(|ground| &ground.name != name))
+        .unwrap_or_default()
+}
+
+fn edit_level(
+    mut cmd: Commands,
+    mut editor: ResMut<LevelEditor>,
+    mouse: Res<Input<MouseButton>>,
+    keyboard: Res<Input<KeyCode>>,
+    cursor: EditorCursor,
+    level_query: Query<Entity, With<LevelEntity>>,
+) {
+    let editor = &mut *editor;
+    let Some(level) = &mut editor.level else {
+        return;
+    };
+    let Some(position) = cursor.world_position() else {
+        return;
+    };
+    let snapped = snap(position, editor.grid);
+    let mut changed = false;
+
+    match editor.tool {
+        EditorTool::Ground => {
+            if mouse.just_pressed(MouseButton::Left) {
+                editor.drag = Some(Drag::Create(snapped));
+            }
+            if mouse.just_released(MouseButton::Left) {
+                if let Some(Drag::Create(start)) = editor.drag.take() {
+                    if let Some((position, size)) = rect_between(start, snapped) {
+                        level.data.grounds.push(GroundData {
+                            name: unused_ground_name(&level.data),
+                            position,
+                            size,
+                            material: Default::default(),
+                            color: None,
+                            one_way: false,
+                            path: None,
+                        });
+                        editor.selected = Some(level.data.grounds.len() - 1);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        EditorTool::Select => {
+            if mouse.just_pressed(MouseButton::Left) {
+                let grounds = &level.data.grounds;
+                let corner = editor
+                    .selected
+                    .and_then(|i| grabbed_corner(&grounds[i], position).map(|c| (i, c)));
+
+                editor.drag = if let Some((i, corner)) = corner {
+                    Some(Drag::Resize(2f32 * grounds[i].position - corner))
+                } else {
+                    // The last ground is drawn on top
+                    editor.selected = grounds
+                        .iter()
+                        .rposition(|ground| contains(ground, position));
+                    editor
+                        .selected
+                        .map(|i| Drag::Move(position - grounds[i].position))
+                };
+            }
+            if mouse.just_released(MouseButton::Left) {
+                editor.drag = None;
+            }
+
+            if let (Some(drag), Some(i)) = (editor.drag, editor.selected) {
+                let ground = &mut level.data.grounds[i];
+                let (new_position, new_size) = match drag {
+                    Drag::Move(offset) => {
+                        // Snaps the corner rather than the center, so grounds line up
+                        let corner = snap(position - offset - ground.size / 2f32, editor.grid);
+                        (corner + ground.size / 2f32, ground.size)
+                    }
+                    Drag::Resize(anchor) => {
+                        rect_between(anchor, snapped).unwrap_or((ground.position, ground.size))
+                    }
+                    Drag::Create(_) => (ground.position, ground.size),
+                };
+
+                if new_position != ground.position || new_size != ground.size {
+                    ground.position = new_position;
+                    ground.size = new_size;
+                    changed = true;
+                }
+            }
+
+            if keyboard.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+                if let Some(i) = editor.selected.take() {
+                    level.data.grounds.remove(i);
+                    editor.drag = None;
+                    changed = true;
+                }
+            }
+        }
+        EditorTool::Spawn => {
+            if mouse.just_pressed(MouseButton::Left) {
+                level.data.spawn = snapped;
+                changed = true;
+            }
+        }
+        EditorTool::Checkpoint => {
+            if mouse.just_pressed(MouseButton::Left) {
+                level.data.checkpoints.push(snapped);
+                changed = true;
+            }
+            if mouse.just_pressed(MouseButton::Right) {
+                let nearest = level.data.checkpoints.iter().position(|checkpoint| {
+                    checkpoint.distance(position) <= CHECKPOINT_PICK_DISTANCE
+                });
+                if let Some(i) = nearest {
+                    level.data.checkpoints.remove(i);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    if changed {
+        for entity in level_query.iter() {
+            cmd.entity(entity).despawn_recursive();
+        }
+        spawn_level(&mut cmd, &level.path, &level.data);
+    }
+}
+
+/// `Ctrl + S` writes the edited level back to its file
+fn save_level(keyboard: Res<Input<KeyCode>>, editor: Res<LevelEditor>) {
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !ctrl || !keyboard.just_pressed(KeyCode::S) {
+        return;
+    }
+    let Some(level) = &editor.level else {
+        return;
+    };
+
+    // A level file saved next to an imported map wouldn't be played, the sequence points at the map
+    let path = Path::new(&level.path);
+    if ldtk::split_level_path(path).is_some() || path.extension().is_some_and(|ext| ext == "tmj") {
+        println!(
+            "Could not save level {}. Imported maps can't be saved by the editor, edit them in \
+            Tiled or LDtk instead",
+            level.path
+        );
+        return;
+    }
+    match level.data.save(path) {
+        Ok(()) => println!("Saved level to {}", level.path),
+        Err(err) => println!("Could not save level to {}. {err}", level.path),
+    }
+}
+
+fn draw_editor(editor: Res<LevelEditor>, cursor: EditorCursor, mut gizmos: Gizmos) {
+    let Some(level) = &editor.level else {
+        return;
+    };
+
+    if let (Some(size), Ok(window), Ok((_, camera))) = (
+        editor.grid,
+        cursor.window_query.get_single(),
+        cursor.camera_query.get_single(),
+    ) {
+        let center = camera.translation().truncate();
+        let half = Vec2::new(window.width(), window.height()) / 2f32;
+        let min = snap(center - half, Some(size)) - size;
+        let max = center + half + size;
+        let color = Color::rgba(1f32, 1f32, 1f32, 0.08f32);
+
+        let mut x = min.x;
+        while x < max.x {
+            gizmos.line_2d(Vec2::new(x, min.y), Vec2::new(x, max.y), color);
+            x += size;
+        }
+        let mut y = min.y;
+        while y < max.y {
+            gizmos.line_2d(Vec2::new(min.x, y), Vec2::new(max.x, y), color);
+            y += size;
+        }
+    }
+
+    for (i, ground) in level.data.grounds.iter().enumerate() {
+        if editor.selected == Some(i) {
+            gizmos.rect_2d(ground.position, 0f32, ground.size, Color::YELLOW);
+            for corner in [
+                Vec2::new(-1f32, -1f32),
+                Vec2::new(1f32, -1f32),
+                Vec2::new(-1f32, 1f32),
+                Vec2::new(1f32, 1f32),
+            ] {
+                let corner = ground.position + corner * ground.size / 2f32;
+                gizmos.rect_2d(corner, 0f32, Vec2::splat(HANDLE_SIZE), Color::YELLOW);
+            }
+        } else {
+            gizmos.rect_2d(ground.position, 0f32, ground.size, Color::DARK_GRAY);
+        }
+    }
+
+    gizmos.circle_2d(level.data.spawn, 12f32, Color::LIME_GREEN);
+
+    if let (Some(Drag::Create(start)), Some(position)) = (editor.drag, cursor.world_position()) {
+        if let Some((center, size)) = rect_between(start, snap(position, editor.grid)) {
+            gizmos.rect_2d(center, 0f32, size, Color::CYAN);
+        }
+    }
+}
+
+#[derive(Component)]
+struct EditorLabel;
+
+fn spawn_editor_label(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16f32,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10f32),
+            bottom: Val::Px(10f32),
+            display: Display::None,
+            ..Default::default()
+        }),
+        EditorLabel,
+        Name::from("Editor label"),
+    ));
+}
+
+fn update_editor_label(
+    editor: Res<LevelEditor>,
+    mut label_query: Query<(&mut Text, &mut Style), With<EditorLabel>>,
+) {
+    for (mut text, mut style) in label_query.iter_mut() {
+        style.display = if editor.enabled {
+            Display::Flex
+        } else {
+            Display::None
+        };
+
+        let path = editor
+            .level
+            .as_ref()
+            .map_or("", |level| level.path.as_str());
+        let grid = editor
+            .grid
+            .map_or("off".to_string(), |size| format!("{size}px"));
+        text.sections[0].value = format!(
+            "Editing {path} | tool: {:?} (1-4) | grid: {grid} (G) | Ctrl+S save | \
+            Enter play from cursor | F10 exit",
+            editor.tool
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::GroundMaterialKind;
+
+    fn ground(name: &str, position: Vec2, size: Vec2) -> GroundData {
+        GroundData {
+            name: name.to_string(),
+            position,
+            size,
+            material: GroundMaterialKind::Normal,
+            color: None,
+            one_way: false,
+            path: None,
+        }
+    }
+
+    #[test]
+    fn snap_rounds_to_the_grid() {
+        let position = Vec2::new(37f32, -12f32);
+
+        assert_eq!(snap(position, Some(25f32)), Vec2::new(25f32, 0f32));
+        assert_eq!(
+            snap(Vec2::new(38f32, -13f32), Some(25f32)),
+            Vec2::new(50f32, -25f32)
+        );
+        assert_eq!(snap(position, None), position);
+    }
+
+    #[test]
+    fn rect_between_any_two_corners() {
+        let rect = Some((Vec2::new(50f32, 25f32), Vec2::new(100f32, 50f32)));
+
+        assert_eq!(rect_between(Vec2::ZERO, Vec2::new(100f32, 50f32)), rect);
+        assert_eq!(
+            rect_between(Vec2::new(100f32, 0f32), Vec2::new(0f32, 50f32)),
+            rect
+        );
+        // Dragging along a line draws nothing
+        assert_eq!(rect_between(Vec2::ZERO, Vec2::new(100f32, 0f32)), None);
+        assert_eq!(rect_between(Vec2::ZERO, Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn grabbed_corner_is_within_the_handle() {
+        let ground = ground("Ground 1", Vec2::ZERO, Vec2::new(100f32, 50f32));
+
+        assert_eq!(
+            grabbed_corner(&ground, Vec2::new(52f32, 27f32)),
+            Some(Vec2::new(50f32, 25f32))
+        );
+        assert_eq!(
+            grabbed_corner(&ground, Vec2::new(-50f32, -25f32 - HANDLE_SIZE)),
+            Some(Vec2::new(-50f32, -25f32))
+        );
+        assert_eq!(grabbed_corner(&ground, Vec2::new(-50f32, -35f32)), None);
+        assert_eq!(grabbed_corner(&ground, Vec2::ZERO), None);
+        assert!(contains(&ground, Vec2::new(50f32, -25f32)));
+        assert!(!contains(&ground, Vec2::new(51f32, 0f32)));
+    }
+
+    #[test]
+    fn unused_ground_name_skips_taken_names() {
+        let mut level = LevelData::default();
+        assert_eq!(unused_ground_name(&level), "Ground 1");
+
+        level.grounds = vec![
+            ground("Ground 2", Vec2::ZERO, Vec2::ONE),
+            ground("Ice 1", Vec2::ZERO, Vec2::ONE),
+        ];
+        assert_eq!(unused_ground_name(&level), "Ground 3");
+
+        level.grounds[1].name = "Ground 3".to_string();
+        assert_eq!(unused_ground_name(&level), "Ground 4");
+    }
+}
@@ -0,0 +1,431 @@
+use bevy::{
+    prelude::*,
+    reflect::{GetPath, ReflectRef},
+    ui::RelativeCursorPosition,
+};
+use std::time::Duration;
+
+use crate::player::{
+    movement::{preset::preset_file_path, CharacterController, CharacterControllerBuilder},
+    Player, PlayerStartupSet,
+};
+
+/// Where the save button of the tuning panel writes the preset, relative to the assets folder
+pub const TUNED_PRESET_PATH: &str = "characters/tuned.preset.ron";
+
+pub(super) struct TuningPanelPlugin;
+
+impl Plugin for TuningPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Startup,
+            spawn_tuning_panel.in_set(PlayerStartupSet::PostPlayer),
+        )
+        .add_systems(
+            Update,
+            (toggle_tuning_panel, drag_sliders, sync_sliders, save_preset).chain(),
+        );
+    }
+}
+
+/// A number somewhere in the reflected fields of the controller
+#[derive(Clone, Debug, PartialEq)]
+pub enum TunedField {
+    Float(String),
+    /// Duration of a timer, in seconds
+    Timer(String),
+    Duration(String),
+}
+
+impl TunedField {
+    pub fn path(&self) -> &str {
+        match self {
+            TunedField::Float(path) | TunedField::Timer(path) | TunedField::Duration(path) => path,
+        }
+    }
+
+    pub fn get(&self, value: &dyn Reflect) -> Option<f32> {
+        let field = value.reflect_path(self.path()).ok()?;
+        match self {
+            TunedField::Float(_) => field.downcast_ref::<f32>().copied(),
+            TunedField::Timer(_) => field
+                .downcast_ref::<Timer>()
+                .map(|timer| timer.duration().as_secs_f32()),
+            TunedField::Duration(_) => field.downcast_ref::<Duration>().map(Duration::as_secs_f32),
+        }
+    }
+
+    pub fn set(&self, value: &mut dyn Reflect, new: f32) {
+        let Ok(field) = value.reflect_path_mut(self.path()) else {
+            return;
+        };
+        match self {
+            TunedField::Float(_) => {
+                if let Some(float) = field.downcast_mut::<f32>() {
+                    *float = new;
+                }
+            }
+            TunedField::Timer(_) => {
+                if let Some(timer) = field.downcast_mut::<Timer>() {
+                    timer.set_duration(Duration::from_secs_f32(new));
+                }
+            }
+            TunedField::Duration(_) => {
+                if let Some(duration) = field.downcast_mut::<Duration>() {
+                    *duration = Duration::from_secs_f32(new);
+                }
+            }
+        }
+    }
+}
+
+/// Every number in `value` that can be tuned, walking nested structs like `Vec2`
+pub fn tunable_fields(value: &dyn Reflect) -> Vec<TunedField> {
+    let mut fields = Vec::new();
+    collect_fields(value, String::new(), &mut fields);
+    fields
+}
+
+fn collect_fields(value: &dyn Reflect, path: String, fields: &mut Vec<TunedField>) {
+    if value.is::<f32>() {
+        fields.push(TunedField::Float(path));
+    } else if value.is::<Timer>() {
+        fields.push(TunedField::Timer(path));
+    } else if value.is::<Duration>() {
+        fields.push(TunedField::Duration(path));
+    } else if let ReflectRef::Struct(value) = value.reflect_ref() {
+        for i in 0..value.field_len() {
+            let (Some(name), Some(field)) = (value.name_at(i), value.field_at(i)) else {
+                continue;
+            };
+            let path = if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{path}.{name}")
+            };
+            collect_fields(field, path, fields);
+        }
+    }
+}
+
+#[derive(Component)]
+struct TuningPanel;
+
+#[derive(Component)]
+struct SavePresetButton;
+
+/// Shows the jump the tuned controller makes
+#[derive(Component)]
+struct JumpArcText;
+
+/// Track of a slider. Clicking or dragging on it sets the field
+#[derive(Component, Clone, Debug)]
+struct TuningSlider {
+    field: TunedField,
+    min: f32,
+    max: f32,
+    fill: Entity,
+    value_text: Entity,
+}
+
+fn text_style(size: f32) -> TextStyle {
+    TextStyle {
+        font_size: size,
+        color: Color::WHITE,
+        ..Default::default()
+    }
+}
+
+fn spawn_tuning_panel(mut cmd: Commands, player_query: Query<&CharacterController, With<Player>>) {
+    let Ok(controller) = player_query.get_single() else {
+        return;
+    };
+
+    let panel = cmd
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(10f32),
+                    top: Val::Px(10f32),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4f32),
+                    padding: UiRect::all(Val::Px(8f32)),
+                    ..Default::default()
+                },
+                background_color: Color::rgba(0f32, 0f32, 0f32, 0.75f32).into(),
+                ..Default::default()
+            },
+            TuningPanel,
+            Name::from("Tuning panel"),
+        ))
+        .id();
+
+    let jump_arc = cmd
+        .spawn((TextBundle::from_section("", text_style(14f32)), JumpArcText))
+        .id();
+    cmd.entity(panel).add_child(jump_arc);
+
+    for field in tunable_fields(controller) {
+        let value = field.get(controller).unwrap_or_default();
+        // Room to go well past the starting value, and some room for values that start at zero
+        let max = (value.abs() * 3f32).max(1f32);
+        // A character without a size would have a collider without area
+        let min = if field.path().starts_with("size") {
+            1f32
+        } else {
+            0f32
+        };
+
+        let row = cmd
+            .spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8f32),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .id();
+        let label = cmd
+            .spawn(
+                TextBundle::from_section(field.path(), text_style(14f32)).with_style(Style {
+                    width: Val::Px(220f32),
+                    ..Default::default()
+                }),
+            )
+            .id();
+        let fill = cmd
+            .spawn(NodeBundle {
+                style: Style {
+                    height: Val::Percent(100f32),
+                    ..Default::default()
+                },
+                background_color: Color::rgb(0.3f32, 0.45f32, 0.7f32).into(),
+                ..Default::default()
+            })
+            .id();
+        let value_text = cmd
+            .spawn(TextBundle::from_section("", text_style(14f32)))
+            .id();
+        let track = cmd
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(160f32),
+                        height: Val::Px(12f32),
+                        ..Default::default()
+                    },
+                    background_color: Color::rgb(0.2f32, 0.2f32, 0.25f32).into(),
+                    ..Default::default()
+                },
+                RelativeCursorPosition::default(),
+                TuningSlider {
+                    field,
+                    min,
+                    max,
+                    fill,
+                    value_text,
+                },
+            ))
+            .add_child(fill)
+            .id();
+
+        cmd.entity(row).push_children(&[label, track, value_text]);
+        cmd.entity(panel).add_child(row);
+    }
+
+    let save = cmd
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    margin: UiRect::top(Val::Px(6f32)),
+                    padding: UiRect::all(Val::Px(4f32)),
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                background_color: Color::rgb(0.15f32, 0.15f32, 0.2f32).into(),
+                ..Default::default()
+            },
+            SavePresetButton,
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                format!("Save preset to {TUNED_PRESET_PATH}"),
+                text_style(14f32),
+            ));
+        })
+        .id();
+    cmd.entity(panel).add_child(save);
+}
+
+fn toggle_tuning_panel(
+    keyboard: Res<Input<KeyCode>>,
+    mut panel_query: Query<&mut Style, With<TuningPanel>>,
+) {
+    if !keyboard.just_pressed(KeyCode::F9) {
+        return;
+    }
+
+    for mut style in panel_query.iter_mut() {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+}
+
+fn drag_sliders(
+    mut cmd: Commands,
+    slider_query: Query<(&TuningSlider, &Interaction, &RelativeCursorPosition)>,
+    mut player_query: Query<(Entity, &mut CharacterController), With<Player>>,
+) {
+    let Ok((player, mut controller)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    for (slider, interaction, cursor) in slider_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(position) = cursor.normalized else {
+            continue;
+        };
+
+        let value = slider.min + position.x.clamp(0f32, 1f32) * (slider.max - slider.min);
+        slider.field.set(&mut *controller, value);
+
+        if slider.field.path().starts_with("size") {
+            cmd.entity(player).insert(controller.collider());
+        }
+    }
+}
+
+fn sync_sliders(
+    slider_query: Query<&TuningSlider>,
+    player_query: Query<&CharacterController, (With<Player>, Changed<CharacterController>)>,
+    mut fill_query: Query<&mut Style>,
+    mut text_query: Query<&mut Text, Without<JumpArcText>>,
+    mut jump_arc_query: Query<&mut Text, With<JumpArcText>>,
+) {
+    let Ok(controller) = player_query.get_single() else {
+        return;
+    };
+
+    let jump_arc = CharacterControllerBuilder::from_controller(controller).jump_arc();
+    for mut text in jump_arc_query.iter_mut() {
+        text.sections[0].value = format!("Jumping {jump_arc}");
+    }
+
+    for slider in slider_query.iter() {
+        let value = slider.field.get(controller).unwrap_or_default();
+        let t = ((value - slider.min) / (slider.max - slider.min)).clamp(0f32, 1f32);
+
+        if let Ok(mut style) = fill_query.get_mut(slider.fill) {
+            style.width = Val::Percent(t * 100f32);
+        }
+        if let Ok(mut text) = text_query.get_mut(slider.value_text) {
+            text.sections[0].value = format!("{value:.3}");
+        }
+    }
+}
+
+fn save_preset(
+    button_query: Query<&Interaction, (With<SavePresetButton>, Changed<Interaction>)>,
+    player_query: Query<&CharacterController, With<Player>>,
+) {
+    if !button_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    let Ok(controller) = player_query.get_single() else {
+        return;
+    };
+
+    let path = preset_file_path(TUNED_PRESET_PATH);
+    match CharacterControllerBuilder::from_controller(controller).save(&path) {
+        Ok(()) => println!("Saved character preset to {}", path.display()),
+        Err(err) => println!(
+            "Could not save character preset to {}. {err}",
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller() -> CharacterController {
+        CharacterControllerBuilder::default().build()
+    }
+
+    fn field(fields: &[TunedField], path: &str) -> TunedField {
+        fields
+            .iter()
+            .find(|field| field.path() == path)
+            .cloned()
+            .unwrap_or_else(|| panic!("no tunable field {path}"))
+    }
+
+    #[test]
+    fn nested_fields_are_tunable() {
+        let fields = tunable_fields(&controller());
+
+        assert_eq!(
+            field(&fields, "jump_force"),
+            TunedField::Float("jump_force".to_string())
+        );
+        assert_eq!(
+            field(&fields, "size.x"),
+            TunedField::Float("size.x".to_string())
+        );
+        assert_eq!(
+            field(&fields, "coyote_timer"),
+            TunedField::Timer("coyote_timer".to_string())
+        );
+        assert_eq!(
+            field(&fields, "surface_checker.cast_distance"),
+            TunedField::Float("surface_checker.cast_distance".to_string())
+        );
+    }
+
+    #[test]
+    fn set_round_trips_through_get() {
+        let mut controller = controller();
+
+        for field in tunable_fields(&controller) {
+            field.set(&mut controller, 0.25f32);
+            assert_eq!(field.get(&controller), Some(0.25f32), "{field:?}");
+        }
+        assert_eq!(controller.jump_force, 0.25f32);
+        assert_eq!(controller.coyote_timer.duration().as_secs_f32(), 0.25f32);
+    }
+
+    #[test]
+    fn tuned_preset_loads_back() {
+        let mut controller = controller();
+        let fields = tunable_fields(&controller);
+        field(&fields, "jump_force").set(&mut controller, 1234f32);
+        field(&fields, "size.x").set(&mut controller, 40f32);
+        field(&fields, "coyote_timer").set(&mut controller, 0.5f32);
+        field(&fields, "surface_checker.cast_distance").set(&mut controller, 7f32);
+
+        let path = std::env::temp_dir().join(format!("tuned-{}.preset.ron", std::process::id()));
+        let builder = CharacterControllerBuilder::from_controller(&controller);
+        builder.save(&path).unwrap();
+        let loaded = CharacterControllerBuilder::load(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, builder);
+        let tuned = loaded.build();
+        assert_eq!(tuned.jump_force, 1234f32);
+        assert_eq!(tuned.size.x, 40f32);
+        assert_eq!(tuned.coyote_timer.duration().as_secs_f32(), 0.5f32);
+        assert_eq!(tuned.surface_checker.cast_distance, 7f32);
+    }
+}
@@ -1,10 +1,34 @@
 use bevy::{app::AppExit, prelude::*};
 use std::time::Duration;
 
+use crate::{
+    key_sequence::{KeySequencePlugin, KeySequences, KeyTrigger},
+    menu::{AppState, QuitReturn},
+};
+
+/// Name of the exit chord in [`KeySequences`]
+pub const EXIT_SEQUENCE: &str = "exit";
+
+/// Exits when `keys` are held together `press_count` times within `reset_timer_duration` of the
+/// first press. [`ExitMode::Confirm`] and [`ExitMode::ToMenu`] need the menu's [`AppState`] and
+/// [`QuitReturn`], and quit right away without them
 pub struct ExitPlugin {
     pub keys: Vec<KeyCode>,
     pub reset_timer_duration: Duration,
     pub press_count: usize,
+    pub mode: ExitMode,
+}
+
+/// What happens when the exit chord is pressed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExitMode {
+    /// Quit right away
+    #[default]
+    Immediate,
+    /// Open a dialog asking whether to quit
+    Confirm,
+    /// Go back to the main menu, or quit when already there
+    ToMenu,
 }
 
 impl Default for ExitPlugin {
@@ -13,56 +37,162 @@ impl Default for ExitPlugin {
             keys: vec![KeyCode::ControlLeft, KeyCode::L],
             reset_timer_duration: Duration::from_secs_f32(0.5f32),
             press_count: 1,
+            mode: ExitMode::default(),
         }
     }
 }
 
 impl Plugin for ExitPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ExitKeys {
-            keys: self.keys.clone(),
-            reset_timer: Timer::new(self.reset_timer_duration, TimerMode::Once),
-            press_count: self.press_count,
+        if !app.is_plugin_added::<KeySequencePlugin>() {
+            app.add_plugins(KeySequencePlugin);
+        }
+        app.world.resource_mut::<KeySequences>().register(
+            EXIT_SEQUENCE,
+            KeyTrigger::chord(self.keys.clone()).times(self.press_count, self.reset_timer_duration),
+        );
+
+        app.insert_resource(ExitSettings {
+            mode: self.mode,
+            pending: None,
         })
-        .add_systems(Update, exit_system);
+        .add_event::<RequestExit>()
+        .add_event::<BeforeExit>()
+        .add_systems(Update, (exit_system, handle_exit_requests).chain())
+        .add_systems(Last, finish_exit);
     }
 }
 
-#[derive(Resource)]
-pub struct ExitKeys {
-    pub keys: Vec<KeyCode>,
-    pub reset_timer: Timer,
-    pub press_count: usize,
+/// Asks the [`ExitPlugin`] to quit. Unconfirmed requests go through the plugin's [`ExitMode`]
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RequestExit {
+    pub confirmed: bool,
 }
 
-fn exit_system(
-    mut exit: EventWriter<AppExit>,
-    mut keys: ResMut<ExitKeys>,
-    mut count: Local<usize>,
-    keyboard: Res<Input<KeyCode>>,
-    time: Res<Time>,
-) {
-    keys.reset_timer
-        .tick(Duration::from_secs_f32(time.delta_seconds()));
+/// Sent one frame before the app exits, so other plugins get a chance to flush their saves
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BeforeExit;
 
-    if keys.reset_timer.just_finished() {
-        keys.reset_timer.reset();
-        *count = 0
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ExitSettings {
+    pub mode: ExitMode,
+    /// Frames left until `AppExit` is sent, after `BeforeExit` went out
+    pending: Option<u8>,
+}
+
+fn exit_system(mut exit: EventWriter<RequestExit>, sequences: Res<KeySequences>) {
+    if sequences.just_triggered(EXIT_SEQUENCE) {
+        exit.send(RequestExit { confirmed: false });
     }
+}
 
-    for key in keys.keys.iter() {
-        if !keyboard.pressed(*key) {
-            return;
-        }
+fn handle_exit_requests(
+    mut requests: EventReader<RequestExit>,
+    mut before_exit: EventWriter<BeforeExit>,
+    mut settings: ResMut<ExitSettings>,
+    state: Option<Res<State<AppState>>>,
+    next_state: Option<ResMut<NextState<AppState>>>,
+    quit_return: Option<ResMut<QuitReturn>>,
+) {
+    // Several requests in one frame act like one
+    let Some(confirmed) = requests
+        .iter()
+        .map(|request| request.confirmed)
+        .reduce(|a, b| a || b)
+    else {
+        return;
+    };
+    if settings.pending.is_some() {
+        return;
     }
 
-    keys.keys.iter().for_each(|key| {
-        if keyboard.just_pressed(*key) {
-            *count += 1;
-            if *count >= keys.press_count {
-                exit.send(AppExit);
+    let quit = confirmed
+        || match (settings.mode, state.zip(next_state).zip(quit_return)) {
+            (ExitMode::Immediate, _) => true,
+            (_, None) => {
+                println!("No menu to handle {:?} exits, quitting", settings.mode);
+                true
             }
-            return;
-        }
-    });
+            (ExitMode::Confirm, Some(((state, mut next_state), mut quit_return))) => {
+                let state = *state.get();
+                if state != AppState::ConfirmQuit {
+                    quit_return.0 = state;
+                    next_state.set(AppState::ConfirmQuit);
+                }
+                false
+            }
+            (ExitMode::ToMenu, Some(((state, mut next_state), _))) => {
+                if *state.get() == AppState::MainMenu {
+                    true
+                } else {
+                    next_state.set(AppState::MainMenu);
+                    false
+                }
+            }
+        };
+
+    if quit {
+        before_exit.send(BeforeExit);
+        settings.pending = Some(1);
+    }
+}
+
+/// Exits a frame after `BeforeExit` was sent, so every system has seen it no matter its order
+fn finish_exit(mut exit: EventWriter<AppExit>, mut settings: ResMut<ExitSettings>) {
+    match settings.pending {
+        Some(0) => exit.send(AppExit),
+        Some(frames) => settings.pending = Some(frames - 1),
+        None => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::InputPlugin;
+
+    fn app(mode: ExitMode) -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            InputPlugin,
+            ExitPlugin {
+                mode,
+                ..Default::default()
+            },
+        ));
+        app
+    }
+
+    /// Requests an exit and runs frames until the app exits, returning how many it took
+    fn frames_to_exit(app: &mut App) -> Option<usize> {
+        app.world.send_event(RequestExit { confirmed: false });
+        (1..=3).find(|_| {
+            app.update();
+            !app.world.resource::<Events<AppExit>>().is_empty()
+        })
+    }
+
+    #[test]
+    fn immediate_exit_needs_no_menu() {
+        assert_eq!(frames_to_exit(&mut app(ExitMode::Immediate)), Some(2));
+    }
+
+    #[test]
+    fn menu_modes_quit_without_a_menu() {
+        assert_eq!(frames_to_exit(&mut app(ExitMode::Confirm)), Some(2));
+        assert_eq!(frames_to_exit(&mut app(ExitMode::ToMenu)), Some(2));
+    }
+
+    #[test]
+    fn confirm_opens_the_dialog() {
+        let mut app = app(ExitMode::Confirm);
+        app.add_state::<AppState>().init_resource::<QuitReturn>();
+
+        assert_eq!(frames_to_exit(&mut app), None);
+        assert_eq!(
+            *app.world.resource::<State<AppState>>().get(),
+            AppState::ConfirmQuit
+        );
+    }
 }
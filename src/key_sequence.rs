@@ -0,0 +1,310 @@
+use bevy::{input::InputSystem, prelude::*, utils::HashMap};
+use std::{collections::VecDeque, time::Duration};
+
+/// Adds the [`KeySequences`] resource and checks its patterns every frame. Check for a finished
+/// pattern with [`KeySequences::just_triggered`] anywhere after `PreUpdate`
+pub struct KeySequencePlugin;
+
+impl Plugin for KeySequencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeySequences>()
+            .add_systems(PreUpdate, detect_key_sequences.after(InputSystem));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyPattern {
+    /// Every key held at once, pressed in any order. Pressing several of the keys on the same
+    /// frame completes the chord once
+    Chord(Vec<KeyCode>),
+    /// Keys pressed one after another, like a cheat code. Pressing any other key breaks it
+    Sequence(Vec<KeyCode>),
+}
+
+impl KeyPattern {
+    pub fn keys(&self) -> &[KeyCode] {
+        match self {
+            KeyPattern::Chord(keys) | KeyPattern::Sequence(keys) => keys,
+        }
+    }
+}
+
+/// A [`KeyPattern`] that has to be completed `times` times within `window`. The window starts on
+/// the first key press of the pattern, and everything is forgotten if it runs out
+#[derive(Clone, Debug)]
+pub struct KeyTrigger {
+    pub pattern: KeyPattern,
+    pub times: usize,
+    pub window: Duration,
+
+    completions: usize,
+    recent: VecDeque<KeyCode>,
+    timer: Option<Timer>,
+    triggered: bool,
+}
+
+impl KeyTrigger {
+    pub fn new(pattern: KeyPattern) -> Self {
+        Self {
+            pattern,
+            times: 1,
+            window: Duration::MAX,
+            completions: 0,
+            recent: VecDeque::new(),
+            timer: None,
+            triggered: false,
+        }
+    }
+
+    pub fn chord(keys: impl Into<Vec<KeyCode>>) -> Self {
+        Self::new(KeyPattern::Chord(keys.into()))
+    }
+
+    pub fn sequence(keys: impl Into<Vec<KeyCode>>) -> Self {
+        Self::new(KeyPattern::Sequence(keys.into()))
+    }
+
+    /// Requires the pattern to be completed `times` times, all within `window`
+    pub fn times(mut self, times: usize, window: Duration) -> Self {
+        self.times = times.max(1);
+        self.window = window;
+        self
+    }
+
+    /// Whether the trigger finished on the current frame
+    pub fn just_triggered(&self) -> bool {
+        self.triggered
+    }
+
+    pub fn reset(&mut self) {
+        self.completions = 0;
+        self.recent.clear();
+        self.timer = None;
+    }
+
+    pub fn update(&mut self, keyboard: &Input<KeyCode>, delta: Duration) {
+        self.triggered = false;
+
+        if let Some(timer) = &mut self.timer {
+            if timer.tick(delta).finished() {
+                self.reset();
+            }
+        }
+
+        let started = match &self.pattern {
+            KeyPattern::Chord(keys) => update_chord(keys, keyboard, &mut self.completions),
+            KeyPattern::Sequence(keys) => {
+                update_sequence(keys, keyboard, &mut self.recent, &mut self.completions)
+            }
+        };
+
+        if started && self.timer.is_none() {
+            self.timer = Some(Timer::new(self.window, TimerMode::Once));
+        }
+        if self.completions >= self.times {
+            self.reset();
+            self.triggered = true;
+        }
+    }
+}
+
+/// Returns whether a key of the chord was pressed
+fn update_chord(keys: &[KeyCode], keyboard: &Input<KeyCode>, completions: &mut usize) -> bool {
+    if !keyboard.any_just_pressed(keys.iter().copied()) {
+        return false;
+    }
+
+    if keys.iter().all(|key| keyboard.pressed(*key)) {
+        *completions += 1;
+    }
+    true
+}
+
+/// Returns whether the sequence is in progress after this frame's presses. `recent` holds the
+/// presses matching the start of the sequence
+fn update_sequence(
+    keys: &[KeyCode],
+    keyboard: &Input<KeyCode>,
+    recent: &mut VecDeque<KeyCode>,
+    completions: &mut usize,
+) -> bool {
+    for key in keyboard.get_just_pressed() {
+        recent.push_back(*key);
+
+        // The longest end of the recent presses that starts the sequence, so that an extra press
+        // of the first key doesn't lose progress
+        let progress = (0..=recent.len().min(keys.len()))
+            .rev()
+            .find(|&len| {
+                recent
+                    .iter()
+                    .skip(recent.len() - len)
+                    .eq(keys[..len].iter())
+            })
+            .unwrap_or(0);
+        recent.drain(..recent.len() - progress);
+
+        if progress == keys.len() {
+            *completions += 1;
+            recent.clear();
+        }
+    }
+
+    !recent.is_empty() || *completions > 0
+}
+
+/// Named [`KeyTrigger`]s, updated from the keyboard every frame
+#[derive(Resource, Clone, Debug, Default)]
+pub struct KeySequences {
+    triggers: HashMap<String, KeyTrigger>,
+}
+
+impl KeySequences {
+    pub fn register(&mut self, name: &str, trigger: KeyTrigger) -> &mut Self {
+        self.triggers.insert(name.to_string(), trigger);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&KeyTrigger> {
+        self.triggers.get(name)
+    }
+
+    pub fn just_triggered(&self, name: &str) -> bool {
+        self.get(name).is_some_and(KeyTrigger::just_triggered)
+    }
+}
+
+fn detect_key_sequences(
+    mut sequences: ResMut<KeySequences>,
+    keyboard: Res<Input<KeyCode>>,
+    time: Res<Time>,
+) {
+    for trigger in sequences.triggers.values_mut() {
+        trigger.update(&keyboard, time.delta());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(100);
+
+    /// Runs a frame with only `keys` held, all pressed on this frame
+    fn press(trigger: &mut KeyTrigger, keyboard: &mut Input<KeyCode>, keys: &[KeyCode]) -> bool {
+        wait(trigger, keyboard, keys, FRAME)
+    }
+
+    /// Runs a frame lasting `delta` with only `keys` held, all pressed on this frame
+    fn wait(
+        trigger: &mut KeyTrigger,
+        keyboard: &mut Input<KeyCode>,
+        keys: &[KeyCode],
+        delta: Duration,
+    ) -> bool {
+        keyboard.clear();
+        keyboard.release_all();
+        for key in keys {
+            keyboard.press(*key);
+        }
+        trigger.update(keyboard, delta);
+        trigger.just_triggered()
+    }
+
+    #[test]
+    fn chord_keys_on_the_same_frame_count_once() {
+        let mut keyboard = Input::default();
+        let mut trigger =
+            KeyTrigger::chord([KeyCode::ControlLeft, KeyCode::K]).times(2, Duration::from_secs(1));
+
+        assert!(!press(
+            &mut trigger,
+            &mut keyboard,
+            &[KeyCode::ControlLeft, KeyCode::K]
+        ));
+        assert!(!press(&mut trigger, &mut keyboard, &[]));
+        assert!(press(
+            &mut trigger,
+            &mut keyboard,
+            &[KeyCode::ControlLeft, KeyCode::K]
+        ));
+    }
+
+    #[test]
+    fn chord_needs_every_key() {
+        let mut keyboard = Input::default();
+        let mut trigger = KeyTrigger::chord([KeyCode::ControlLeft, KeyCode::K]);
+
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::K]));
+        assert!(press(
+            &mut trigger,
+            &mut keyboard,
+            &[KeyCode::ControlLeft, KeyCode::K]
+        ));
+    }
+
+    #[test]
+    fn sequence_completes_in_order() {
+        let mut keyboard = Input::default();
+        let mut trigger = KeyTrigger::sequence([KeyCode::Up, KeyCode::Up, KeyCode::Down]);
+
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Up]));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Up]));
+        // An extra press of the first key keeps the progress
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Up]));
+        assert!(press(&mut trigger, &mut keyboard, &[KeyCode::Down]));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Down]));
+    }
+
+    #[test]
+    fn sequence_resets_on_wrong_key() {
+        let mut keyboard = Input::default();
+        let mut trigger = KeyTrigger::sequence([KeyCode::Up, KeyCode::Up, KeyCode::Down]);
+
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Up]));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Up]));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Left]));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Down]));
+
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Up]));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Up]));
+        assert!(press(&mut trigger, &mut keyboard, &[KeyCode::Down]));
+    }
+
+    #[test]
+    fn times_fires_on_the_last_press_in_the_window() {
+        let mut keyboard = Input::default();
+        let mut trigger = KeyTrigger::chord([KeyCode::Escape]).times(3, Duration::from_secs(1));
+
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Escape]));
+        assert!(!press(&mut trigger, &mut keyboard, &[]));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Escape]));
+        assert!(!press(&mut trigger, &mut keyboard, &[]));
+        assert!(press(&mut trigger, &mut keyboard, &[KeyCode::Escape]));
+    }
+
+    #[test]
+    fn times_forgets_presses_after_the_window() {
+        let mut keyboard = Input::default();
+        let window = Duration::from_secs(1);
+        let mut trigger = KeyTrigger::chord([KeyCode::Escape]).times(2, window);
+
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Escape]));
+        assert!(!wait(&mut trigger, &mut keyboard, &[], window));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Escape]));
+        assert!(press(&mut trigger, &mut keyboard, &[KeyCode::Escape]));
+    }
+
+    #[test]
+    fn window_starts_on_the_first_press() {
+        let mut keyboard = Input::default();
+        let window = Duration::from_secs(1);
+        let mut trigger = KeyTrigger::chord([KeyCode::Escape]).times(2, window);
+
+        // Idle time before the first press doesn't count towards the window
+        assert!(!wait(&mut trigger, &mut keyboard, &[], window * 5));
+        assert!(!press(&mut trigger, &mut keyboard, &[KeyCode::Escape]));
+        assert!(!wait(&mut trigger, &mut keyboard, &[], window - FRAME * 2));
+        assert!(press(&mut trigger, &mut keyboard, &[KeyCode::Escape]));
+    }
+}
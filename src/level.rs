@@ -1,45 +1,844 @@
-use bevy::prelude::*;
-use bevy_rapier2d::prelude::*;
+use bevy::{
+    asset::FileAssetIo, ecs::system::SystemParam, prelude::*, render::texture::DEFAULT_IMAGE_HANDLE,
+};
+use bevy_rapier2d::{prelude::*, rapier::math::Vector};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::menu::AppState;
+use crate::player::{
+    health::{HazardContacts, Health},
+    Player, PlayerStartupSet,
+};
+
+pub mod collectibles;
+pub mod generator;
+mod import;
+pub mod ldtk;
+pub mod moving_platforms;
+pub mod reachability;
+pub mod tiled;
+pub mod tiles;
+use collectibles::*;
+use ldtk::LdtkEntities;
+use moving_platforms::*;
+use tiles::*;
+
+pub const LEVEL_SEQUENCE_PATH: &str = "assets/levels/sequence.ron";
+
+/// Where a level file path points to. Relative paths are relative to the folder the assets
+/// folder is in, which is found the same way as the asset server does
+pub fn resolve_path(path: impl AsRef<Path>) -> PathBuf {
+    FileAssetIo::get_base_path().join(path)
+}
 
 pub(super) struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, init);
+        app.add_state::<LevelState>()
+            .add_systems(Startup, init.before(PlayerStartupSet::PrePlayer))
+            .add_plugins((CollectiblesPlugin, MovingPlatformsPlugin, TilesPlugin))
+            .add_systems(OnEnter(LevelState::Loading), load_current_level)
+            .add_systems(OnEnter(LevelState::LevelComplete), complete_level)
+            .add_systems(
+                Update,
+                (activate_checkpoints, reach_goal, enter_neighbours)
+                    .run_if(in_state(LevelState::Playing)),
+            )
+            .add_systems(
+                Update,
+                advance_level
+                    .run_if(in_state(LevelState::LevelComplete))
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .init_resource::<SpawnPoint>()
+            .init_resource::<ActiveCheckpoint>()
+            .init_resource::<LevelRooms>()
+            .init_resource::<LdtkEntities>()
+            .register_type::<GroundMaterial>()
+            .register_type::<Hazard>();
     }
 }
 
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LevelState {
+    /// The current level of the [`LevelSequence`] is being spawned
+    #[default]
+    Loading,
+    Playing,
+    /// The goal was reached, the next level loads once [`LevelSequence::complete_delay`] has passed
+    LevelComplete,
+}
+
+/// Loads the level sequence. Without one, no level is loaded
 fn init(mut cmd: Commands) {
-    cmd.spawn((
+    match LevelSequence::load(LEVEL_SEQUENCE_PATH) {
+        Ok(sequence) => cmd.insert_resource(sequence),
+        Err(err) => println!("Could not load level sequence {LEVEL_SEQUENCE_PATH}. {err}"),
+    }
+}
+
+/// Despawns the previous level, spawns the current level of the sequence and puts the player back
+/// at its spawn point, unless they walked in from a neighbouring level. If the level can't be
+/// loaded, the previous one is kept and played on. Without a previous level or a level sequence,
+/// the game goes back to the main menu
+#[allow(clippy::too_many_arguments)]
+fn load_current_level(
+    mut cmd: Commands,
+    level_query: Query<Entity, With<LevelEntity>>,
+    mut player_query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut Health,
+            &mut HazardContacts,
+        ),
+        With<Player>,
+    >,
+    sequence: Option<ResMut<LevelSequence>>,
+    ldtk_entities: Res<LdtkEntities>,
+    mut rooms: ResMut<LevelRooms>,
+    mut next_state: ResMut<NextState<LevelState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    let Some(mut sequence) = sequence else {
+        println!("Could not load a level without the level sequence {LEVEL_SEQUENCE_PATH}");
+        next_app_state.set(AppState::MainMenu);
+        // Leaves `Loading`, so playing again tries again
+        next_state.set(LevelState::Playing);
+        return;
+    };
+    let path = sequence.current_path().to_string();
+    let level = match LevelData::load_with_entities(&path, &ldtk_entities) {
+        Ok(level) => level,
+        Err(err) => {
+            println!("Could not load level {path}. {err}");
+            sequence.entry = None;
+            match sequence.loaded {
+                Some(loaded) => sequence.current = loaded,
+                None => next_app_state.set(AppState::MainMenu),
+            }
+            // Walking back out of the level would only try the broken neighbour again
+            rooms.neighbours.retain(|neighbour| neighbour.level != path);
+            next_state.set(LevelState::Playing);
+            return;
+        }
+    };
+    sequence.loaded = Some(sequence.current);
+    let entry = sequence.entry.take();
+
+    for entity in level_query.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+    spawn_level(&mut cmd, &path, &level);
+
+    if let Some(entry) = entry {
+        // The player keeps moving, and comes back where they walked in if they die
+        cmd.insert_resource(SpawnPoint(entry));
+        next_state.set(LevelState::Playing);
+        return;
+    }
+
+    for (mut transform, mut vel, mut health, mut contacts) in player_query.iter_mut() {
+        transform.translation = level.spawn.extend(transform.translation.z);
+        transform.rotation = Quat::default();
+        vel.linvel = Vec2::ZERO;
+        vel.angvel = 0f32;
+        health.current = health.max;
+        contacts.0.clear();
+    }
+
+    next_state.set(LevelState::Playing);
+}
+
+fn reach_goal(
+    mut collisions: EventReader<CollisionEvent>,
+    goal_query: Query<(), With<LevelGoal>>,
+    player_query: Query<(), With<Player>>,
+    mut next_state: ResMut<NextState<LevelState>>,
+) {
+    for event in collisions.iter() {
+        let CollisionEvent::Started(a, b, _) = *event else {
+            continue;
+        };
+
+        if player_query.contains(a) && goal_query.contains(b)
+            || player_query.contains(b) && goal_query.contains(a)
+        {
+            next_state.set(LevelState::LevelComplete);
+        }
+    }
+}
+
+fn complete_level(mut sequence: ResMut<LevelSequence>, progress: Res<LevelProgress>) {
+    println!(
+        "Completed {} with {}/{} collectibles",
+        progress.level,
+        progress.collected(),
+        progress.total()
+    );
+    sequence.timer.reset();
+}
+
+fn advance_level(
+    mut sequence: ResMut<LevelSequence>,
+    mut next_state: ResMut<NextState<LevelState>>,
+    time: Res<Time>,
+) {
+    if !sequence.timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    sequence.advance();
+    next_state.set(LevelState::Loading);
+}
+
+/// Loads the neighbouring level the player walked into once they leave the current one
+fn enter_neighbours(
+    player_query: Query<&Transform, With<Player>>,
+    rooms: Res<LevelRooms>,
+    mut sequence: ResMut<LevelSequence>,
+    mut next_state: ResMut<NextState<LevelState>>,
+    mut reported: Local<Option<String>>,
+) {
+    let Some(bounds) = rooms.bounds else {
+        return;
+    };
+
+    for transform in player_query.iter() {
+        let position = transform.translation.truncate();
+        if bounds.rect().contains(position) {
+            continue;
+        }
+        let Some(neighbour) = rooms
+            .neighbours
+            .iter()
+            .find(|neighbour| neighbour.bounds.rect().contains(position))
+        else {
+            continue;
+        };
+        let Some(index) = sequence
+            .levels
+            .iter()
+            .position(|path| *path == neighbour.level)
+        else {
+            if reported.as_ref() != Some(&neighbour.level) {
+                println!(
+                    "Neighbouring level {} is not in the level sequence",
+                    neighbour.level
+                );
+                *reported = Some(neighbour.level.clone());
+            }
+            continue;
+        };
+
+        sequence.current = index;
+        sequence.entry = Some(position);
+        next_state.set(LevelState::Loading);
+        return;
+    }
+}
+
+/// Spawns `level`, loaded from `path`
+pub fn spawn_level(cmd: &mut Commands, path: &str, level: &LevelData) {
+    cmd.insert_resource(SpawnPoint(level.spawn));
+    cmd.insert_resource(LevelRooms {
+        bounds: level.bounds,
+        neighbours: level.neighbours.clone(),
+    });
+
+    cmd.insert_resource(ActiveCheckpoint::default());
+    cmd.insert_resource(LevelProgress::new(path, level));
+
+    for ground in level.grounds.iter() {
+        spawn_ground(cmd, ground);
+    }
+    for hazard in level.hazards.iter() {
+        spawn_hazard(cmd, hazard);
+    }
+    for checkpoint in level.checkpoints.iter() {
+        spawn_checkpoint(cmd, *checkpoint);
+    }
+    for collectible in level.collectibles.iter() {
+        spawn_collectible(cmd, collectible);
+    }
+    if let Some(goal) = level.goal {
+        spawn_goal(cmd, goal);
+    }
+    for tile in level.tiles.iter() {
+        spawn_tile(cmd, &level.tilesets, tile);
+    }
+}
+
+pub fn spawn_ground(cmd: &mut Commands, ground: &GroundData) -> Entity {
+    let material = ground.material.material();
+
+    let mut entity = cmd.spawn((
         SpriteBundle {
             sprite: Sprite {
-                color: Color::rgb_u8(205, 255, 150),
-                custom_size: Some(Vec2::new(500f32, 25f32)),
+                color: ground.color.unwrap_or(ground.material.color()),
+                custom_size: Some(ground.size),
                 ..Default::default()
             },
-            transform: Transform::from_xyz(0f32, -50f32, 0f32),
+            texture: DEFAULT_IMAGE_HANDLE.typed(),
+            transform: Transform::from_translation(ground.position.extend(0f32)),
             ..Default::default()
         },
-        Collider::cuboid(250f32, 12.75f32),
+        Collider::cuboid(ground.size.x / 2f32, ground.size.y / 2f32),
+        Restitution {
+            coefficient: material.restitution,
+            combine_rule: CoefficientCombineRule::Max,
+        },
+        material,
         Ground,
-        Name::from("Platform"),
+        LevelEntity,
+        Name::from(ground.name.as_str()),
     ));
+    if ground.one_way {
+        entity.insert((OneWayPlatform, ActiveHooks::MODIFY_SOLVER_CONTACTS));
+    }
+    if let Some(path) = &ground.path {
+        entity.insert((
+            RigidBody::KinematicPositionBased,
+            MovingPlatform::new(ground.position, path.clone()),
+        ));
+    }
+    entity.id()
+}
 
+pub fn spawn_hazard(cmd: &mut Commands, hazard: &HazardData) -> Entity {
     cmd.spawn((
         SpriteBundle {
             sprite: Sprite {
-                color: Color::rgb_u8(205, 255, 150),
-                custom_size: Some(Vec2::new(25f32, 1000f32)),
+                color: hazard.kind.color(),
+                custom_size: Some(hazard.size),
                 ..Default::default()
             },
-            transform: Transform::from_xyz(-300f32, 0f32, 0f32),
+            texture: DEFAULT_IMAGE_HANDLE.typed(),
+            transform: Transform::from_translation(hazard.position.extend(0f32)),
             ..Default::default()
         },
-        Collider::cuboid(12.75f32, 500f32),
-        Ground,
-        Name::from("Wall"),
-    ));
+        Collider::cuboid(hazard.size.x / 2f32, hazard.size.y / 2f32),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        hazard.hazard(),
+        LevelEntity,
+        Name::from(hazard.name.as_str()),
+    ))
+    .id()
+}
+
+pub fn spawn_checkpoint(cmd: &mut Commands, position: Vec2) -> Entity {
+    cmd.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Checkpoint::INACTIVE_COLOR,
+                custom_size: Some(Checkpoint::SIZE),
+                ..Default::default()
+            },
+            texture: DEFAULT_IMAGE_HANDLE.typed(),
+            transform: Transform::from_translation(position.extend(-1f32)),
+            ..Default::default()
+        },
+        Collider::cuboid(Checkpoint::SIZE.x / 2f32, Checkpoint::SIZE.y / 2f32),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        Checkpoint,
+        LevelEntity,
+        Name::from("Checkpoint"),
+    ))
+    .id()
+}
+
+pub fn spawn_goal(cmd: &mut Commands, position: Vec2) -> Entity {
+    cmd.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: LevelGoal::COLOR,
+                custom_size: Some(LevelGoal::SIZE),
+                ..Default::default()
+            },
+            texture: DEFAULT_IMAGE_HANDLE.typed(),
+            transform: Transform::from_translation(position.extend(-1f32)),
+            ..Default::default()
+        },
+        Collider::cuboid(LevelGoal::SIZE.x / 2f32, LevelGoal::SIZE.y / 2f32),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        LevelGoal,
+        LevelEntity,
+        Name::from("Goal"),
+    ))
+    .id()
 }
 
+/// Makes the last checkpoint the player touched the respawn point
+fn activate_checkpoints(
+    mut collisions: EventReader<CollisionEvent>,
+    mut checkpoint_query: Query<(&Transform, &mut Sprite), With<Checkpoint>>,
+    player_query: Query<(), With<Player>>,
+    mut active: ResMut<ActiveCheckpoint>,
+) {
+    for event in collisions.iter() {
+        let CollisionEvent::Started(a, b, _) = *event else {
+            continue;
+        };
+        let checkpoint = match (player_query.contains(a), player_query.contains(b)) {
+            (true, _) => b,
+            (_, true) => a,
+            _ => continue,
+        };
+        let Ok((transform, _)) = checkpoint_query.get(checkpoint) else {
+            continue;
+        };
+
+        let position = transform.translation.truncate();
+        if active.0 == Some(position) {
+            continue;
+        }
+        active.0 = Some(position);
+
+        for (transform, mut sprite) in checkpoint_query.iter_mut() {
+            sprite.color = if transform.translation.truncate() == position {
+                Checkpoint::ACTIVE_COLOR
+            } else {
+                Checkpoint::INACTIVE_COLOR
+            };
+        }
+    }
+}
+
+/// Marks everything spawned from a [`LevelData`], so it can be despawned when the level changes
+#[derive(Component)]
+pub struct LevelEntity;
+
 #[derive(Component)]
 pub struct Ground;
+
+/// Ground that can be jumped through from below and landed on from above
+#[derive(Component)]
+pub struct OneWayPlatform;
+
+/// Physics hooks of the game, which let characters pass through [`OneWayPlatform`]s from below
+#[derive(SystemParam)]
+pub struct LevelPhysicsHooks<'w, 's> {
+    one_way_query: Query<'w, 's, (), With<OneWayPlatform>>,
+}
+
+impl BevyPhysicsHooks for LevelPhysicsHooks<'_, '_> {
+    fn modify_solver_contacts(&self, context: ContactModificationContextView) {
+        // Contact normals point from the first collider to the second
+        let allowed_normal = if self.one_way_query.contains(context.collider1()) {
+            Vector::y()
+        } else if self.one_way_query.contains(context.collider2()) {
+            -Vector::y()
+        } else {
+            return;
+        };
+        context
+            .raw
+            .update_as_oneway_platform(&allowed_normal, 0.1f32);
+    }
+}
+
+/// Damages characters that touch it. Hazards are sensors and report contacts through Rapier
+/// collision events
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct Hazard {
+    pub damage: u32,
+    /// Speed the character is knocked away from the hazard with when hit
+    pub knockback: f32,
+}
+
+#[derive(Component)]
+pub struct Checkpoint;
+
+impl Checkpoint {
+    pub const SIZE: Vec2 = Vec2::new(15f32, 60f32);
+    pub const INACTIVE_COLOR: Color = Color::rgb(0.5f32, 0.5f32, 0.5f32);
+    pub const ACTIVE_COLOR: Color = Color::rgb(1f32, 0.85f32, 0.2f32);
+}
+
+/// Completes the level when the player touches it
+#[derive(Component)]
+pub struct LevelGoal;
+
+impl LevelGoal {
+    pub const SIZE: Vec2 = Vec2::new(30f32, 60f32);
+    pub const COLOR: Color = Color::rgb(0.3f32, 1f32, 0.4f32);
+}
+
+/// Position of the last checkpoint the player touched in the current level
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct ActiveCheckpoint(pub Option<Vec2>);
+
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct SpawnPoint(pub Vec2);
+
+/// Where the current level is and which levels the player can walk into from it
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LevelRooms {
+    pub bounds: Option<LevelBounds>,
+    pub neighbours: Vec<LevelNeighbour>,
+}
+
+/// Where a dead player comes back: the active checkpoint, or the level's spawn point
+pub fn respawn_point(spawn: &SpawnPoint, checkpoint: &ActiveCheckpoint) -> Vec2 {
+    checkpoint.0.unwrap_or(spawn.0)
+}
+
+/// How standing on a piece of ground changes a character's movement. The multipliers are applied
+/// to the matching `CharacterController` values
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GroundMaterial {
+    pub acceleration_multi: f32,
+    pub decceleration_multi: f32,
+    pub max_speed_multi: f32,
+    pub jump_force_multi: f32,
+    pub restitution: f32,
+    /// Landing on the ground jumps automatically with this force multiplier
+    pub bounce: Option<f32>,
+}
+
+impl GroundMaterial {
+    pub const NORMAL: Self = Self {
+        acceleration_multi: 1f32,
+        decceleration_multi: 1f32,
+        max_speed_multi: 1f32,
+        jump_force_multi: 1f32,
+        restitution: 0f32,
+        bounce: None,
+    };
+
+    pub const ICE: Self = Self {
+        acceleration_multi: 0.35f32,
+        decceleration_multi: 0.08f32,
+        max_speed_multi: 1.2f32,
+        ..Self::NORMAL
+    };
+
+    pub const MUD: Self = Self {
+        acceleration_multi: 0.6f32,
+        decceleration_multi: 2f32,
+        max_speed_multi: 0.45f32,
+        jump_force_multi: 0.8f32,
+        ..Self::NORMAL
+    };
+
+    pub const STICKY: Self = Self {
+        decceleration_multi: 4f32,
+        max_speed_multi: 0.7f32,
+        jump_force_multi: 0.55f32,
+        ..Self::NORMAL
+    };
+
+    pub fn bouncy(multi: f32) -> Self {
+        Self {
+            restitution: 0.3f32,
+            bounce: Some(multi),
+            ..Self::NORMAL
+        }
+    }
+}
+
+impl Default for GroundMaterial {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
+/// Material of a ground in a level file, either one of the presets or a custom material
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum GroundMaterialKind {
+    #[default]
+    Normal,
+    Ice,
+    Mud,
+    Sticky,
+    Bouncy(f32),
+    Custom(GroundMaterial),
+}
+
+impl GroundMaterialKind {
+    pub fn material(&self) -> GroundMaterial {
+        match self {
+            GroundMaterialKind::Normal => GroundMaterial::NORMAL,
+            GroundMaterialKind::Ice => GroundMaterial::ICE,
+            GroundMaterialKind::Mud => GroundMaterial::MUD,
+            GroundMaterialKind::Sticky => GroundMaterial::STICKY,
+            GroundMaterialKind::Bouncy(multi) => GroundMaterial::bouncy(*multi),
+            GroundMaterialKind::Custom(material) => *material,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            GroundMaterialKind::Normal | GroundMaterialKind::Custom(_) => {
+                Color::rgb_u8(205, 255, 150)
+            }
+            GroundMaterialKind::Ice => Color::rgb_u8(190, 235, 255),
+            GroundMaterialKind::Mud => Color::rgb_u8(120, 85, 50),
+            GroundMaterialKind::Sticky => Color::rgb_u8(180, 120, 210),
+            GroundMaterialKind::Bouncy(_) => Color::rgb_u8(255, 130, 180),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GroundData {
+    pub name: String,
+    pub position: Vec2,
+    pub size: Vec2,
+    #[serde(default)]
+    pub material: GroundMaterialKind,
+    #[serde(default)]
+    pub color: Option<Color>,
+    /// Only collides with characters coming from above
+    #[serde(default)]
+    pub one_way: bool,
+    /// Makes the ground move back and forth along the path
+    #[serde(default)]
+    pub path: Option<GroundPath>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum HazardKind {
+    #[default]
+    Spikes,
+    Lava,
+}
+
+impl HazardKind {
+    pub fn hazard(&self) -> Hazard {
+        match self {
+            HazardKind::Spikes => Hazard {
+                damage: 1,
+                knockback: 350f32,
+            },
+            HazardKind::Lava => Hazard {
+                damage: 2,
+                knockback: 550f32,
+            },
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            HazardKind::Spikes => Color::rgb_u8(170, 170, 185),
+            HazardKind::Lava => Color::rgb_u8(255, 100, 30),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HazardData {
+    pub name: String,
+    pub position: Vec2,
+    pub size: Vec2,
+    #[serde(default)]
+    pub kind: HazardKind,
+    /// Overrides the damage of the hazard kind
+    #[serde(default)]
+    pub damage: Option<u32>,
+}
+
+impl HazardData {
+    pub fn hazard(&self) -> Hazard {
+        let hazard = self.kind.hazard();
+        Hazard {
+            damage: self.damage.unwrap_or(hazard.damage),
+            ..hazard
+        }
+    }
+}
+
+/// Everything needed to spawn a level, as stored in level files
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LevelData {
+    /// Identifies the level in save files
+    #[serde(default)]
+    pub name: String,
+    pub spawn: Vec2,
+    pub grounds: Vec<GroundData>,
+    #[serde(default)]
+    pub hazards: Vec<HazardData>,
+    #[serde(default)]
+    pub checkpoints: Vec<Vec2>,
+    #[serde(default)]
+    pub collectibles: Vec<CollectibleData>,
+    /// Levels without a goal can't be completed
+    #[serde(default)]
+    pub goal: Option<Vec2>,
+    #[serde(default)]
+    pub tilesets: Vec<TilesetData>,
+    #[serde(default)]
+    pub tiles: Vec<TileData>,
+    /// Area of the level in a world of rooms. Leaving it loads the neighbour the player walks into
+    #[serde(default)]
+    pub bounds: Option<LevelBounds>,
+    #[serde(default)]
+    pub neighbours: Vec<LevelNeighbour>,
+}
+
+/// Corners of a level in the world
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl LevelBounds {
+    pub fn rect(&self) -> Rect {
+        Rect::from_corners(self.min, self.max)
+    }
+}
+
+/// Level next to another one, that the player walks into when leaving through the shared edge
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelNeighbour {
+    /// Path of the level, as in the level sequence
+    pub level: String,
+    pub bounds: LevelBounds,
+}
+
+impl LevelData {
+    /// Loads a RON level file, imports a Tiled JSON map if the extension is `tmj`, or imports a
+    /// level of an LDtk project for paths like `world.ldtk#Level_0`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        Self::load_with_entities(path, &LdtkEntities::default())
+    }
+
+    /// Like [`Self::load`], with the entities registered for LDtk imports
+    pub fn load_with_entities(
+        path: impl AsRef<Path>,
+        ldtk_entities: &LdtkEntities,
+    ) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        if let Some((project, level)) = ldtk::split_level_path(path) {
+            return ldtk::import(project, level, ldtk_entities).map_err(LevelError::Ldtk);
+        }
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("tmj") => return tiled::import(path).map_err(LevelError::Tiled),
+            Some("tmx") => {
+                return Err(LevelError::Tiled(tiled::TiledError::Map(
+                    "only JSON maps can be imported, export the map as .tmj".to_string(),
+                )))
+            }
+            _ => {}
+        }
+
+        let source = std::fs::read_to_string(resolve_path(path)).map_err(LevelError::Io)?;
+        ron::de::from_str(&source).map_err(LevelError::Parse)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LevelError> {
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(LevelError::Serialize)?;
+        std::fs::write(resolve_path(path), source).map_err(LevelError::Io)
+    }
+}
+
+/// Level files in the order they are played, as stored in the sequence file
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct LevelSequence {
+    pub levels: Vec<String>,
+    #[serde(skip)]
+    pub current: usize,
+    /// How long the game waits on a completed level before loading the next one
+    #[serde(skip, default = "LevelSequence::complete_delay")]
+    pub timer: Timer,
+    /// Where the player walked into the current level from a neighbouring one
+    #[serde(skip)]
+    pub entry: Option<Vec2>,
+    /// Index of the level that is spawned, which is played on if the next one fails to load
+    #[serde(skip)]
+    pub loaded: Option<usize>,
+}
+
+impl LevelSequence {
+    pub fn new(levels: Vec<String>) -> Self {
+        Self {
+            levels,
+            current: 0,
+            timer: Self::complete_delay(),
+            entry: None,
+            loaded: None,
+        }
+    }
+
+    pub fn complete_delay() -> Timer {
+        Timer::from_seconds(1.5f32, TimerMode::Once)
+    }
+
+    /// Loads a RON sequence file, or plays every level of an LDtk project. LDtk projects in the
+    /// sequence stand for all of their levels
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        let levels = if ldtk::is_project(path) {
+            vec![path.to_string_lossy().to_string()]
+        } else {
+            let source = std::fs::read_to_string(resolve_path(path)).map_err(LevelError::Io)?;
+            let sequence: Self = ron::de::from_str(&source).map_err(LevelError::Parse)?;
+            sequence.levels
+        };
+
+        let sequence = Self::new(expand_level_paths(&levels)?);
+        if sequence.levels.is_empty() {
+            return Err(LevelError::EmptySequence);
+        }
+        Ok(sequence)
+    }
+
+    pub fn current_path(&self) -> &str {
+        &self.levels[self.current]
+    }
+
+    /// Moves on to the next level, going back to the first one after the last
+    pub fn advance(&mut self) {
+        self.current = (self.current + 1) % self.levels.len();
+    }
+}
+
+/// Replaces the paths of LDtk projects with the paths of their levels, in project order
+pub fn expand_level_paths(paths: &[String]) -> Result<Vec<String>, LevelError> {
+    let mut levels = Vec::new();
+    for path in paths {
+        if ldtk::is_project(Path::new(path)) {
+            levels.extend(ldtk::level_paths(Path::new(path)).map_err(LevelError::Ldtk)?);
+        } else {
+            levels.push(path.clone());
+        }
+    }
+    Ok(levels)
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    EmptySequence,
+    Tiled(tiled::TiledError),
+    Ldtk(ldtk::LdtkError),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(err) => write!(f, "Could not access level file: {err}"),
+            LevelError::Parse(err) => write!(f, "Invalid level file: {err}"),
+            LevelError::Serialize(err) => write!(f, "Could not serialize level: {err}"),
+            LevelError::EmptySequence => write!(f, "The level sequence has no levels"),
+            LevelError::Tiled(err) => write!(f, "Could not import Tiled map: {err}"),
+            LevelError::Ldtk(err) => write!(f, "Could not import LDtk project: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
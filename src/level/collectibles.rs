@@ -0,0 +1,274 @@
+use bevy::{prelude::*, render::texture::DEFAULT_IMAGE_HANDLE};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::Path,
+};
+
+use super::{resolve_path, LevelData, LevelEntity, LevelError, LevelState};
+use crate::player::Player;
+
+/// Gems collected in every level, kept between runs. Relative to the folder the assets folder is
+/// in, like level paths
+pub const SAVED_GEMS_PATH: &str = "saves/gems.ron";
+
+pub(super) struct CollectiblesPlugin;
+
+impl Plugin for CollectiblesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CollectiblePicked>()
+            .init_resource::<SavedGems>()
+            .init_resource::<LevelProgress>()
+            .add_systems(Startup, load_saved_gems)
+            .add_systems(
+                Update,
+                (
+                    mark_saved_gems,
+                    pick_up_collectibles.run_if(in_state(LevelState::Playing)),
+                    track_progress,
+                )
+                    .chain(),
+            );
+    }
+}
+
+pub fn spawn_collectible(cmd: &mut Commands, collectible: &CollectibleData) -> Entity {
+    let size = collectible.kind.size();
+
+    cmd.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: collectible.kind.color(),
+                custom_size: Some(size),
+                ..Default::default()
+            },
+            texture: DEFAULT_IMAGE_HANDLE.typed(),
+            transform: Transform::from_translation(collectible.position.extend(-1f32))
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            ..Default::default()
+        },
+        Collider::cuboid(size.x / 2f32, size.y / 2f32),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        Collectible {
+            kind: collectible.kind,
+            name: collectible.name.clone(),
+        },
+        LevelEntity,
+        Name::from(collectible.name.as_str()),
+    ))
+    .id()
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CollectibleKind {
+    #[default]
+    Coin,
+    /// Hidden gem, usually one per level. Collected gems are saved to disk
+    Gem,
+}
+
+impl CollectibleKind {
+    pub fn size(&self) -> Vec2 {
+        match self {
+            CollectibleKind::Coin => Vec2::splat(14f32),
+            CollectibleKind::Gem => Vec2::splat(20f32),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            CollectibleKind::Coin => Color::rgb_u8(255, 215, 60),
+            CollectibleKind::Gem => Color::rgb_u8(80, 255, 220),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CollectibleData {
+    /// Gems are saved by name, so it has to be unique within the level
+    pub name: String,
+    pub position: Vec2,
+    #[serde(default)]
+    pub kind: CollectibleKind,
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Collectible {
+    pub kind: CollectibleKind,
+    pub name: String,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct CollectiblePicked {
+    pub entity: Entity,
+    pub kind: CollectibleKind,
+    pub name: String,
+}
+
+/// What has been collected in the current level
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LevelProgress {
+    /// Name of the level, or its path if it has none. Gems are saved under it
+    pub level: String,
+    pub coins: usize,
+    pub total_coins: usize,
+    pub gems: HashSet<String>,
+    pub total_gems: usize,
+}
+
+impl LevelProgress {
+    /// `path` is where `level` was loaded from, and keys its saved gems if it has no name
+    pub fn new(path: &str, level: &LevelData) -> Self {
+        let count = |kind| {
+            level
+                .collectibles
+                .iter()
+                .filter(|collectible| collectible.kind == kind)
+                .count()
+        };
+
+        Self {
+            level: if level.name.is_empty() {
+                path.to_owned()
+            } else {
+                level.name.clone()
+            },
+            total_coins: count(CollectibleKind::Coin),
+            total_gems: count(CollectibleKind::Gem),
+            ..Default::default()
+        }
+    }
+
+    pub fn collected(&self) -> usize {
+        self.coins + self.gems.len()
+    }
+
+    pub fn total(&self) -> usize {
+        self.total_coins + self.total_gems
+    }
+}
+
+/// Names of the gems collected in each level, by [`LevelProgress::level`]
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedGems(pub BTreeMap<String, BTreeSet<String>>);
+
+impl SavedGems {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let source = std::fs::read_to_string(resolve_path(path)).map_err(LevelError::Io)?;
+        ron::de::from_str(&source).map_err(LevelError::Parse)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LevelError> {
+        let path = resolve_path(path);
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(LevelError::Serialize)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(LevelError::Io)?;
+        }
+        std::fs::write(path, source).map_err(LevelError::Io)
+    }
+
+    pub fn contains(&self, level: &str, gem: &str) -> bool {
+        self.0.get(level).is_some_and(|gems| gems.contains(gem))
+    }
+}
+
+fn load_saved_gems(mut saved: ResMut<SavedGems>) {
+    match SavedGems::load(SAVED_GEMS_PATH) {
+        Ok(loaded) => *saved = loaded,
+        Err(LevelError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => println!("Could not load saved gems {SAVED_GEMS_PATH}. {err}"),
+    }
+}
+
+/// Gems collected in an earlier run are shown faded and count as collected from the start
+fn mark_saved_gems(
+    mut gem_query: Query<(&Collectible, &mut Sprite), Added<Collectible>>,
+    saved: Res<SavedGems>,
+    mut progress: ResMut<LevelProgress>,
+) {
+    for (collectible, mut sprite) in gem_query.iter_mut() {
+        if collectible.kind != CollectibleKind::Gem
+            || !saved.contains(&progress.level, &collectible.name)
+        {
+            continue;
+        }
+
+        sprite.color.set_a(0.35f32);
+        progress.gems.insert(collectible.name.clone());
+    }
+}
+
+fn pick_up_collectibles(
+    mut cmd: Commands,
+    mut collisions: EventReader<CollisionEvent>,
+    collectible_query: Query<&Collectible>,
+    player_query: Query<(), With<Player>>,
+    mut picked: EventWriter<CollectiblePicked>,
+) {
+    for event in collisions.iter() {
+        let CollisionEvent::Started(a, b, _) = *event else {
+            continue;
+        };
+        let entity = match (player_query.contains(a), player_query.contains(b)) {
+            (true, _) => b,
+            (_, true) => a,
+            _ => continue,
+        };
+        let Ok(collectible) = collectible_query.get(entity) else {
+            continue;
+        };
+
+        cmd.entity(entity).despawn_recursive();
+        picked.send(CollectiblePicked {
+            entity,
+            kind: collectible.kind,
+            name: collectible.name.clone(),
+        });
+    }
+}
+
+fn track_progress(
+    mut picked: EventReader<CollectiblePicked>,
+    mut progress: ResMut<LevelProgress>,
+    mut saved: ResMut<SavedGems>,
+) {
+    for event in picked.iter() {
+        match event.kind {
+            CollectibleKind::Coin => progress.coins += 1,
+            CollectibleKind::Gem => {
+                progress.gems.insert(event.name.clone());
+
+                let level = progress.level.clone();
+                if !saved.0.entry(level).or_default().insert(event.name.clone()) {
+                    continue;
+                }
+                if let Err(err) = saved.save(SAVED_GEMS_PATH) {
+                    println!("Could not save gems to {SAVED_GEMS_PATH}. {err}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_is_keyed_by_name_or_path() {
+        let mut level = LevelData::default();
+        assert_eq!(
+            LevelProgress::new("assets/levels/tiled/example.tmj", &level).level,
+            "assets/levels/tiled/example.tmj"
+        );
+
+        level.name = "Level 1".to_owned();
+        assert_eq!(
+            LevelProgress::new("assets/levels/level_1.ron", &level).level,
+            "Level 1"
+        );
+    }
+}
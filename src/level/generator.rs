@@ -0,0 +1,414 @@
+use bevy::prelude::*;
+
+use super::{
+    collectibles::{CollectibleData, CollectibleKind},
+    reachability::ReachabilityReport,
+    Checkpoint, GroundData, GroundMaterialKind, HazardData, HazardKind, LevelData, LevelGoal,
+};
+use crate::player::movement::{
+    jump_arc::JumpArc, preset::read_player_preset, CharacterControllerBuilder,
+};
+
+/// Grid everything is placed on, the same as the level editor's
+const GRID: f32 = 25f32;
+/// Ground tops stay between these heights
+const MIN_HEIGHT: f32 = -100f32;
+const MAX_HEIGHT: f32 = 400f32;
+/// Grounds reach down to here, so the terrain reads as solid
+const GROUND_BOTTOM: f32 = -200f32;
+const HAZARD_HEIGHT: f32 = 20f32;
+/// Every this many chunks, a flat chunk with a checkpoint is put in
+const CHECKPOINT_SPACING: usize = 4;
+
+/// What a level is generated with, apart from the seed
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorSettings {
+    /// Chunks between the start and the goal
+    pub chunks: usize,
+    /// Fraction of the character's jump height and distance that gaps, steps and hazards use.
+    /// Close to 1 needs perfect jumps
+    pub difficulty: f32,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            chunks: 12,
+            difficulty: 0.7f32,
+        }
+    }
+}
+
+/// Generates the level for `seed` with the player's preset and checks that everything in it can
+/// be reached. Saves the level to `path` if there is one, and returns whether it passed
+pub fn generate_level_file(seed: &str, path: Option<&str>) -> bool {
+    let Ok(seed) = seed.parse::<u64>() else {
+        println!("Invalid seed `{seed}`, expected a positive integer");
+        return false;
+    };
+
+    let controller = read_player_preset();
+    let level = generate(seed, &controller, &GeneratorSettings::default());
+    let report = ReachabilityReport::new(&level, &controller);
+    if report.is_ok() {
+        println!("{}: everything can be reached", level.name);
+    } else {
+        println!("{}:\n{report}", level.name);
+    }
+
+    if let Some(path) = path {
+        match level.save(path) {
+            Ok(()) => println!("Saved level to {path}"),
+            Err(err) => {
+                println!("Could not save level to {path}. {err}");
+                return false;
+            }
+        }
+    }
+    report.is_ok()
+}
+
+/// Generates a level out of random chunks of ground, gaps, steps, floating platforms and spikes.
+/// Gaps, steps and hazards are kept within what `controller` can jump. The same seed, settings
+/// and controller always give the same level
+pub fn generate(
+    seed: u64,
+    controller: &CharacterControllerBuilder,
+    settings: &GeneratorSettings,
+) -> LevelData {
+    let mut generator = Generator {
+        rng: Rng(seed),
+        controller,
+        arc: controller.jump_arc(),
+        difficulty: settings.difficulty.clamp(0f32, 1f32),
+        level: LevelData {
+            name: format!("Generated {seed}"),
+            ..Default::default()
+        },
+        x: 0f32,
+        height: 0f32,
+    };
+
+    generator.start();
+    for chunk in 1..=settings.chunks {
+        if chunk % CHECKPOINT_SPACING == 0 {
+            generator.checkpoint();
+            continue;
+        }
+
+        match generator.rng.pick(&ChunkKind::WEIGHTS) {
+            ChunkKind::Flat => generator.flat(),
+            ChunkKind::Gap => generator.gap(),
+            ChunkKind::Step => generator.step(),
+            ChunkKind::Stones => generator.stones(),
+            ChunkKind::Spikes => generator.spikes(),
+        }
+    }
+    generator.end();
+    generator.level
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ChunkKind {
+    /// Ground with coins
+    Flat,
+    /// A pit to jump over, to ground at a different height
+    Gap,
+    /// Ground higher or lower than the last
+    Step,
+    /// Floating platforms over a pit
+    Stones,
+    /// Ground with spikes to jump over
+    Spikes,
+}
+
+impl ChunkKind {
+    /// Kinds with how often they come up
+    const WEIGHTS: [(ChunkKind, u32); 5] = [
+        (ChunkKind::Flat, 2),
+        (ChunkKind::Gap, 3),
+        (ChunkKind::Step, 2),
+        (ChunkKind::Stones, 2),
+        (ChunkKind::Spikes, 2),
+    ];
+}
+
+/// SplitMix64, so the same seed gives the same level on every platform and version
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number from `min` to `max`, both included, or `max` if it is the smaller one
+    fn range(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return max;
+        }
+        min + (self.next() % (max - min + 1) as u64) as i32
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+
+    fn pick<T: Copy>(&mut self, weights: &[(T, u32)]) -> T {
+        let total = weights
+            .iter()
+            .map(|(_, weight)| *weight as u64)
+            .sum::<u64>();
+        let mut roll = self.next() % total;
+        for (item, weight) in weights {
+            if roll < *weight as u64 {
+                return *item;
+            }
+            roll -= *weight as u64;
+        }
+        weights[weights.len() - 1].0
+    }
+}
+
+struct Generator<'a> {
+    rng: Rng,
+    controller: &'a CharacterControllerBuilder,
+    arc: JumpArc,
+    difficulty: f32,
+    level: LevelData,
+    /// Right edge and top of the ground the last chunk ended on
+    x: f32,
+    height: f32,
+}
+
+impl Generator<'_> {
+    /// Highest step up, in grid cells
+    fn max_rise(&self) -> i32 {
+        (self.arc.height * self.difficulty / GRID).floor() as i32
+    }
+
+    /// Widest jump, in grid cells, to ground `rise` cells above the takeoff
+    fn max_gap(&self, rise: i32) -> i32 {
+        let distance = self.arc.distance_at(rise as f32 * GRID).unwrap_or(0f32);
+        (distance * self.difficulty / GRID).floor() as i32
+    }
+
+    /// A change of height in grid cells, keeping the ground between the lowest and highest
+    /// heights. Drops can be twice as deep as rises are high
+    fn random_rise(&mut self) -> i32 {
+        let max_rise = self.max_rise();
+        let lowest = ((MIN_HEIGHT - self.height) / GRID).ceil() as i32;
+        let highest = ((MAX_HEIGHT - self.height) / GRID).floor() as i32;
+        self.rng
+            .range((-2 * max_rise).max(lowest), max_rise.min(highest))
+    }
+
+    /// Stand height of the character's center on the current ground
+    fn stand_height(&self) -> f32 {
+        self.height + self.controller.size.y / 2f32
+    }
+
+    fn ground_name(&self) -> String {
+        format!("Ground {}", self.level.grounds.len() + 1)
+    }
+
+    /// Adds ground `cells` wide at the current height, reaching down to the bottom of the level
+    fn ground(&mut self, cells: i32) {
+        let size = Vec2::new(cells as f32 * GRID, self.height - GROUND_BOTTOM);
+        self.level.grounds.push(GroundData {
+            name: self.ground_name(),
+            position: Vec2::new(self.x + size.x / 2f32, GROUND_BOTTOM + size.y / 2f32),
+            size,
+            material: GroundMaterialKind::Normal,
+            color: None,
+            one_way: false,
+            path: None,
+        });
+        self.x += size.x;
+    }
+
+    fn coin(&mut self, position: Vec2) {
+        self.level.collectibles.push(CollectibleData {
+            name: format!("Coin {}", self.level.collectibles.len() + 1),
+            position,
+            kind: CollectibleKind::Coin,
+        });
+    }
+
+    fn start(&mut self) {
+        self.level.spawn = Vec2::new(self.x + 2f32 * GRID, self.stand_height());
+        self.ground(8);
+    }
+
+    fn end(&mut self) {
+        self.gap();
+        self.ground(6);
+        self.level.goal = Some(Vec2::new(
+            self.x - 3f32 * GRID,
+            self.height + LevelGoal::SIZE.y / 2f32,
+        ));
+    }
+
+    fn checkpoint(&mut self) {
+        let start = self.x;
+        self.ground(6);
+        self.level.checkpoints.push(Vec2::new(
+            (start + self.x) / 2f32,
+            self.height + Checkpoint::SIZE.y / 2f32,
+        ));
+    }
+
+    fn flat(&mut self) {
+        let start = self.x;
+        let cells = self.rng.range(5, 10);
+        self.ground(cells);
+
+        // Three coins along the ground, or at the top of a jump
+        let height = if self.rng.chance(50) {
+            self.stand_height()
+        } else {
+            self.stand_height() + self.arc.height * self.difficulty
+        };
+        let middle = (start + self.x) / 2f32;
+        for i in -1..=1 {
+            self.coin(Vec2::new(middle + i as f32 * GRID, height));
+        }
+    }
+
+    fn gap(&mut self) {
+        let rise = self.random_rise();
+        let max_gap = self.max_gap(rise);
+        let gap = self.rng.range(2.min(max_gap), max_gap);
+
+        self.x += gap as f32 * GRID;
+        self.height += rise as f32 * GRID;
+        let cells = self.rng.range(3, 6);
+        self.ground(cells);
+    }
+
+    fn step(&mut self) {
+        let rise = match self.random_rise() {
+            // Always a step, down if up would be too high
+            0 if self.height - GRID >= MIN_HEIGHT => -1,
+            0 => 1,
+            rise => rise,
+        };
+
+        self.height += rise as f32 * GRID;
+        let cells = self.rng.range(3, 6);
+        self.ground(cells);
+    }
+
+    /// Floating platforms, which can be jumped through from below, then ground
+    fn stones(&mut self) {
+        let max_rise = self.max_rise();
+        for _ in 0..self.rng.range(2, 3) {
+            let lowest = ((MIN_HEIGHT - self.height) / GRID).ceil() as i32;
+            let highest = ((MAX_HEIGHT - self.height) / GRID).floor() as i32;
+            let rise = self
+                .rng
+                .range((-max_rise).max(lowest), max_rise.min(highest));
+            let max_gap = self.max_gap(rise);
+            let gap = self.rng.range(2.min(max_gap), max_gap);
+            self.x += gap as f32 * GRID;
+            self.height += rise as f32 * GRID;
+
+            let size = Vec2::new(self.rng.range(2, 4) as f32 * GRID, GRID);
+            let position = Vec2::new(self.x + size.x / 2f32, self.height - size.y / 2f32);
+            self.level.grounds.push(GroundData {
+                name: self.ground_name(),
+                position,
+                size,
+                material: GroundMaterialKind::Normal,
+                color: None,
+                one_way: self.rng.chance(50),
+                path: None,
+            });
+            self.x += size.x;
+
+            // Usually one gem per level, on the first platform
+            if !self
+                .level
+                .collectibles
+                .iter()
+                .any(|collectible| collectible.kind == CollectibleKind::Gem)
+            {
+                self.level.collectibles.push(CollectibleData {
+                    name: "Gem".to_string(),
+                    position: Vec2::new(position.x, self.stand_height()),
+                    kind: CollectibleKind::Gem,
+                });
+            }
+        }
+        self.gap();
+    }
+
+    /// Spikes as wide as the character can jump over from their edge
+    fn spikes(&mut self) {
+        let distance = self.arc.distance_at(HAZARD_HEIGHT).unwrap_or(0f32) * self.difficulty;
+        let max_width = ((distance - self.controller.size.x) / GRID).floor() as i32;
+        if max_width < 1 {
+            self.flat();
+            return;
+        }
+
+        let run_up = 3;
+        let width = self.rng.range(1, max_width);
+        let start = self.x + run_up as f32 * GRID;
+        self.ground(run_up * 2 + width);
+
+        let size = Vec2::new(width as f32 * GRID, HAZARD_HEIGHT);
+        self.level.hazards.push(HazardData {
+            name: format!("Spikes {}", self.level.hazards.len() + 1),
+            position: Vec2::new(start + size.x / 2f32, self.height + size.y / 2f32),
+            size,
+            kind: HazardKind::Spikes,
+            damage: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_level() {
+        let controller = CharacterControllerBuilder::default();
+        let settings = GeneratorSettings::default();
+
+        for seed in 0..10 {
+            assert_eq!(
+                generate(seed, &controller, &settings),
+                generate(seed, &controller, &settings)
+            );
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_levels() {
+        let controller = CharacterControllerBuilder::default();
+        let settings = GeneratorSettings::default();
+
+        let first = generate(1, &controller, &settings);
+        for seed in 2..10 {
+            let level = generate(seed, &controller, &settings);
+            assert_ne!(level.grounds, first.grounds, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn generated_levels_can_be_finished() {
+        let controller = CharacterControllerBuilder::default();
+        let settings = GeneratorSettings::default();
+
+        for seed in 0..20 {
+            let level = generate(seed, &controller, &settings);
+            let report = ReachabilityReport::new(&level, &controller);
+            assert!(report.is_ok(), "seed {seed}:\n{report}");
+        }
+    }
+}
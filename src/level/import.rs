@@ -0,0 +1,142 @@
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+
+use super::{GroundMaterialKind, HazardKind};
+
+/// Material named by a `material` property or field. Bouncy grounds take their multiplier from
+/// `bounce`
+pub(super) fn parse_material(
+    material: Option<&Value>,
+    bounce: Option<&Value>,
+) -> Result<GroundMaterialKind, String> {
+    let Some(material) = material else {
+        return Ok(GroundMaterialKind::Normal);
+    };
+
+    match material.as_str() {
+        Some("Normal") => Ok(GroundMaterialKind::Normal),
+        Some("Ice") => Ok(GroundMaterialKind::Ice),
+        Some("Mud") => Ok(GroundMaterialKind::Mud),
+        Some("Sticky") => Ok(GroundMaterialKind::Sticky),
+        Some("Bouncy") => bounce
+            .and_then(Value::as_f64)
+            .map(|bounce| GroundMaterialKind::Bouncy(bounce as f32))
+            .ok_or_else(|| "bouncy grounds need a float property `bounce`".to_string()),
+        _ => Err(format!(
+            "unknown material {material}, expected Normal, Ice, Mud, Sticky or Bouncy"
+        )),
+    }
+}
+
+/// Hazard kind and damage named by `kind` and `damage` properties or fields. Hazards without a
+/// damage take the damage of their kind
+pub(super) fn parse_hazard(
+    kind: Option<&Value>,
+    damage: Option<&Value>,
+) -> Result<(HazardKind, Option<u32>), String> {
+    let kind = match kind.map(Value::as_str) {
+        None | Some(Some("Spikes")) => HazardKind::Spikes,
+        Some(Some("Lava")) => HazardKind::Lava,
+        Some(_) => return Err("unknown hazard kind, expected Spikes or Lava".to_string()),
+    };
+    let damage = match damage {
+        None => None,
+        Some(damage) => Some(
+            damage
+                .as_u64()
+                .ok_or_else(|| "hazard damage has to be a positive int".to_string())?
+                as u32,
+        ),
+    };
+    Ok((kind, damage))
+}
+
+/// Path of `image` in a map in `dir`, relative to the assets folder
+pub(super) fn asset_path(dir: &Path, image: &str) -> Option<String> {
+    let mut path = PathBuf::new();
+    for component in dir.join(image).components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::CurDir => {}
+            component => path.push(component),
+        }
+    }
+
+    let assets = path.iter().position(|component| component == "assets")?;
+    let path = path.iter().skip(assets + 1).collect::<PathBuf>();
+    Some(path.to_string_lossy().replace('\\', "/"))
+}
+
+/// Covers the solid cells of a grid with rectangles, found greedily row by row. Rectangles are
+/// (column, row, width, height)
+pub(super) fn merge_cells(solid: &[bool], width: usize) -> Vec<(usize, usize, usize, usize)> {
+    let height = solid.len() / width;
+    let mut covered = vec![false; solid.len()];
+    let free = |covered: &[bool], col: usize, row: usize| {
+        solid[row * width + col] && !covered[row * width + col]
+    };
+
+    let mut rects = Vec::new();
+    for row in 0..height {
+        for col in 0..width {
+            if !free(&covered, col, row) {
+                continue;
+            }
+
+            let mut w = 1;
+            while col + w < width && free(&covered, col + w, row) {
+                w += 1;
+            }
+            let mut h = 1;
+            while row + h < height && (col..col + w).all(|c| free(&covered, c, row + h)) {
+                h += 1;
+            }
+
+            for r in row..row + h {
+                covered[r * width + col..r * width + col + w].fill(true);
+            }
+            rects.push((col, row, w, h));
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_cells_into_rectangles() {
+        #[rustfmt::skip]
+        let solid = [
+            true, true, false,
+            true, true, true,
+        ];
+
+        assert_eq!(merge_cells(&solid, 3), vec![(0, 0, 2, 2), (2, 1, 1, 1)]);
+        assert_eq!(merge_cells(&[true; 6], 3), vec![(0, 0, 3, 2)]);
+        assert!(merge_cells(&[false; 6], 3).is_empty());
+    }
+
+    #[test]
+    fn merged_rectangles_cover_each_solid_cell_once() {
+        let width = 7;
+        let solid = (0..width * 5)
+            .map(|i| (i * 7 + i / 3) % 5 < 3)
+            .collect::<Vec<_>>();
+
+        let mut covered = vec![0; solid.len()];
+        for (col, row, w, h) in merge_cells(&solid, width) {
+            for r in row..row + h {
+                for c in col..col + w {
+                    covered[r * width + c] += 1;
+                }
+            }
+        }
+        for (solid, covered) in solid.iter().zip(covered) {
+            assert_eq!(covered, *solid as i32);
+        }
+    }
+}
@@ -0,0 +1,876 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{fmt, path::Path};
+
+use super::{
+    collectibles::{Collectible, CollectibleData, CollectibleKind},
+    import::{asset_path, merge_cells, parse_hazard, parse_material},
+    resolve_path,
+    tiles::{TileData, TilesetData},
+    Checkpoint, Ground, GroundData, GroundMaterialKind, Hazard, HazardData, HazardKind,
+    LevelBounds, LevelData, LevelGoal, LevelNeighbour, SpawnPoint,
+};
+
+/// Separates the project from the level in paths like `world.ldtk#Level_0`
+const LEVEL_SEPARATOR: char = '#';
+/// IntGrid value identifiers that become level geometry
+const INT_GRID_VALUES: &str = "ground, ice, mud, sticky, one_way, spikes or lava";
+/// Neighbour directions of levels next to each other, rather than above, below or overlapping
+const NEIGHBOUR_DIRECTIONS: [&str; 8] = ["n", "s", "e", "w", "ne", "nw", "se", "sw"];
+
+#[derive(Debug)]
+pub enum LdtkError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    /// Something about the project as a whole that can't be imported
+    Project(String),
+    /// Something in a level that can't be imported
+    Level {
+        level: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for LdtkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LdtkError::Io(err) => write!(f, "Could not read project: {err}"),
+            LdtkError::Parse(err) => write!(f, "Invalid project: {err}"),
+            LdtkError::Project(message) => write!(f, "{message}"),
+            LdtkError::Level { level, message } => write!(f, "level {level}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for LdtkError {}
+
+/// An entity of an LDtk level, as given to the importer registered for its identifier
+#[derive(Clone, Debug)]
+pub struct LdtkEntityInstance {
+    pub identifier: String,
+    /// Unique ID of the entity in the project
+    pub iid: String,
+    /// Center of the entity in the world
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Values of the entity's fields, by field identifier
+    pub fields: HashMap<String, Value>,
+}
+
+impl LdtkEntityInstance {
+    /// Value of a field, unless it is unset
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        self.fields.get(name).filter(|value| !value.is_null())
+    }
+
+    /// The `name` field, or the identifier and ID of the entity
+    pub fn name(&self) -> String {
+        match self.field("name").and_then(Value::as_str) {
+            Some(name) => name.to_string(),
+            None => format!("{} {}", self.identifier, self.iid),
+        }
+    }
+
+    fn has_area(&self) -> bool {
+        self.size.x > 0f32 && self.size.y > 0f32
+    }
+}
+
+/// Something LDtk entities can be imported as, registered with [`RegisterLdtkEntity`]
+pub trait LdtkEntity {
+    /// Adds the entity to the level being imported, or explains why it can't be
+    fn import(entity: &LdtkEntityInstance, level: &mut LevelData) -> Result<(), String>;
+}
+
+type Importer = fn(&LdtkEntityInstance, &mut LevelData) -> Result<(), String>;
+
+/// What LDtk entities are imported as, by entity identifier. The game's entities are registered
+/// as `Spawn`, `Checkpoint`, `Goal`, `Coin`, `Gem`, `Hazard` and `Ground`
+#[derive(Resource, Clone)]
+pub struct LdtkEntities {
+    importers: HashMap<String, Importer>,
+}
+
+impl LdtkEntities {
+    /// No registered entities, so importing any entity fails
+    pub fn empty() -> Self {
+        Self {
+            importers: HashMap::new(),
+        }
+    }
+
+    /// Imports entities with the identifier `identifier` as `T`, instead of what they were
+    /// registered as before
+    pub fn register<T: LdtkEntity>(&mut self, identifier: impl Into<String>) -> &mut Self {
+        self.importers.insert(identifier.into(), T::import);
+        self
+    }
+}
+
+impl Default for LdtkEntities {
+    fn default() -> Self {
+        let mut entities = Self::empty();
+        entities
+            .register::<SpawnPoint>("Spawn")
+            .register::<Checkpoint>("Checkpoint")
+            .register::<LevelGoal>("Goal")
+            .register::<Collectible>("Coin")
+            .register::<Collectible>("Gem")
+            .register::<Hazard>("Hazard")
+            .register::<Ground>("Ground");
+        entities
+    }
+}
+
+pub trait RegisterLdtkEntity {
+    /// Imports LDtk entities with the identifier `identifier` as `T`
+    fn register_ldtk_entity<T: LdtkEntity>(&mut self, identifier: &str) -> &mut Self;
+}
+
+impl RegisterLdtkEntity for App {
+    fn register_ldtk_entity<T: LdtkEntity>(&mut self, identifier: &str) -> &mut Self {
+        self.init_resource::<LdtkEntities>();
+        self.world
+            .resource_mut::<LdtkEntities>()
+            .register::<T>(identifier);
+        self
+    }
+}
+
+impl LdtkEntity for SpawnPoint {
+    fn import(entity: &LdtkEntityInstance, level: &mut LevelData) -> Result<(), String> {
+        level.spawn = entity.position;
+        Ok(())
+    }
+}
+
+impl LdtkEntity for Checkpoint {
+    fn import(entity: &LdtkEntityInstance, level: &mut LevelData) -> Result<(), String> {
+        level.checkpoints.push(entity.position);
+        Ok(())
+    }
+}
+
+impl LdtkEntity for LevelGoal {
+    fn import(entity: &LdtkEntityInstance, level: &mut LevelData) -> Result<(), String> {
+        if level.goal.is_some() {
+            return Err("the level already has a goal".to_string());
+        }
+        level.goal = Some(entity.position);
+        Ok(())
+    }
+}
+
+impl LdtkEntity for Collectible {
+    /// The kind comes from the `kind` field, or from the identifier
+    fn import(entity: &LdtkEntityInstance, level: &mut LevelData) -> Result<(), String> {
+        let kind = match entity.field("kind").map(Value::as_str) {
+            None if entity.identifier.eq_ignore_ascii_case("gem") => CollectibleKind::Gem,
+            None | Some(Some("Coin")) => CollectibleKind::Coin,
+            Some(Some("Gem")) => CollectibleKind::Gem,
+            Some(_) => return Err("unknown collectible kind, expected Coin or Gem".to_string()),
+        };
+
+        level.collectibles.push(CollectibleData {
+            name: entity.name(),
+            position: entity.position,
+            kind,
+        });
+        Ok(())
+    }
+}
+
+impl LdtkEntity for Hazard {
+    fn import(entity: &LdtkEntityInstance, level: &mut LevelData) -> Result<(), String> {
+        if !entity.has_area() {
+            return Err("hazards need a width and height".to_string());
+        }
+        let (kind, damage) = parse_hazard(entity.field("kind"), entity.field("damage"))?;
+
+        level.hazards.push(HazardData {
+            name: entity.name(),
+            position: entity.position,
+            size: entity.size,
+            kind,
+            damage,
+        });
+        Ok(())
+    }
+}
+
+impl LdtkEntity for Ground {
+    /// Takes the `material` and `bounce` fields, and the bool field `one_way`
+    fn import(entity: &LdtkEntityInstance, level: &mut LevelData) -> Result<(), String> {
+        if !entity.has_area() {
+            return Err("grounds need a width and height".to_string());
+        }
+
+        level.grounds.push(GroundData {
+            name: entity.name(),
+            position: entity.position,
+            size: entity.size,
+            material: parse_material(entity.field("material"), entity.field("bounce"))?,
+            color: None,
+            one_way: entity
+                .field("one_way")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            path: None,
+        });
+        Ok(())
+    }
+}
+
+/// Whether `path` is a whole LDtk project rather than one of its levels
+pub fn is_project(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "ldtk")
+}
+
+/// Splits paths like `world.ldtk#Level_0` into the project and the identifier of the level.
+/// Paths of a whole project have no level
+pub fn split_level_path(path: &Path) -> Option<(&Path, Option<&str>)> {
+    if is_project(path) {
+        return Some((path, None));
+    }
+
+    let (project, level) = path.to_str()?.rsplit_once(LEVEL_SEPARATOR)?;
+    is_project(Path::new(project)).then_some((Path::new(project), Some(level)))
+}
+
+/// Paths of every level of the project at `path`, in project order
+pub fn level_paths(path: &Path) -> Result<Vec<String>, LdtkError> {
+    let project = read_project(path)?;
+    Ok(project
+        .levels
+        .iter()
+        .map(|level| level_path(path, &level.identifier))
+        .collect())
+}
+
+fn level_path(project: &Path, level: &str) -> String {
+    format!("{}{LEVEL_SEPARATOR}{level}", project.display())
+}
+
+/// Imports a level of an LDtk project, or its first level if `level` is `None`.
+///
+/// IntGrid values become grounds, one-way platforms and hazards by their identifier: `ground`,
+/// `ice`, `mud`, `sticky`, `one_way`, `spikes` or `lava`. Their cells are merged into as few
+/// rectangles as possible. Tiles and auto-layer tiles of visible layers become sprite tiles.
+///
+/// Entities become level data through what is registered for their identifier in `entities`.
+/// Levels without a spawn entity spawn the player in their center.
+///
+/// In worlds laid out freely or as a GridVania, levels keep their place in the world and link to
+/// the levels next to them, which the player walks into when leaving the level
+pub fn import(
+    path: &Path,
+    level: Option<&str>,
+    entities: &LdtkEntities,
+) -> Result<LevelData, LdtkError> {
+    let project = read_project(path)?;
+    let source = match level {
+        Some(identifier) => project
+            .levels
+            .iter()
+            .find(|level| level.identifier == identifier)
+            .ok_or_else(|| {
+                LdtkError::Project(format!("the project has no level `{identifier}`"))
+            })?,
+        None => project
+            .levels
+            .first()
+            .ok_or_else(|| LdtkError::Project("the project has no levels".to_string()))?,
+    };
+
+    let in_world = matches!(
+        project.world_layout.as_deref(),
+        Some("Free") | Some("GridVania")
+    );
+    let mut import = Import {
+        project: &project,
+        source,
+        dir: path.parent().unwrap_or(Path::new("")),
+        // Levels outside a world stand on y = 0, like other level files
+        origin: if in_world {
+            Vec2::new(source.world_x, -source.world_y)
+        } else {
+            Vec2::new(0f32, source.px_hei)
+        },
+        tilesets: HashMap::new(),
+        level: LevelData {
+            name: source.identifier.clone(),
+            ..Default::default()
+        },
+    };
+    import.level.spawn = import.point(source.px_wid / 2f32, source.px_hei / 2f32);
+
+    let Some(layers) = &source.layer_instances else {
+        return Err(import.error("the level has no layers"));
+    };
+    // Layers are listed from the top down
+    for layer in layers.iter().rev() {
+        import.layer(layer, entities)?;
+    }
+
+    if in_world {
+        import.level.bounds = Some(source.bounds());
+        for link in source.neighbours.iter() {
+            if !NEIGHBOUR_DIRECTIONS.contains(&link.dir.as_str()) {
+                continue;
+            }
+            if let Some(neighbour) = project.levels.iter().find(|l| l.iid == link.level_iid) {
+                import.level.neighbours.push(LevelNeighbour {
+                    level: level_path(path, &neighbour.identifier),
+                    bounds: neighbour.bounds(),
+                });
+            }
+        }
+    }
+    Ok(import.level)
+}
+
+fn read_project(path: &Path) -> Result<LdtkProject, LdtkError> {
+    let source = std::fs::read_to_string(resolve_path(path)).map_err(LdtkError::Io)?;
+    let project: LdtkProject = serde_json::from_str(&source).map_err(LdtkError::Parse)?;
+
+    if project.external_levels {
+        return Err(LdtkError::Project(
+            "levels in separate files are not supported, turn off `Save levels to separate files`"
+                .to_string(),
+        ));
+    }
+    if !project.worlds.is_empty() {
+        return Err(LdtkError::Project(
+            "projects with several worlds are not supported".to_string(),
+        ));
+    }
+    Ok(project)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LdtkProject {
+    #[serde(default)]
+    external_levels: bool,
+    /// Not set in projects with several worlds
+    world_layout: Option<String>,
+    #[serde(default)]
+    worlds: Vec<Value>,
+    defs: Definitions,
+    levels: Vec<LdtkLevel>,
+}
+
+#[derive(Deserialize)]
+struct Definitions {
+    layers: Vec<LayerDefinition>,
+    tilesets: Vec<TilesetDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerDefinition {
+    uid: i64,
+    #[serde(default)]
+    int_grid_values: Vec<IntGridValue>,
+}
+
+#[derive(Deserialize)]
+struct IntGridValue {
+    value: i64,
+    identifier: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TilesetDefinition {
+    uid: i64,
+    identifier: String,
+    /// Not set for the embedded icon atlas
+    rel_path: Option<String>,
+    tile_grid_size: f32,
+    #[serde(default)]
+    spacing: f32,
+    #[serde(default)]
+    padding: f32,
+    #[serde(rename = "__cWid")]
+    columns: usize,
+    #[serde(rename = "__cHei")]
+    rows: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LdtkLevel {
+    identifier: String,
+    iid: String,
+    world_x: f32,
+    world_y: f32,
+    px_wid: f32,
+    px_hei: f32,
+    #[serde(rename = "__neighbours", default)]
+    neighbours: Vec<NeighbourLink>,
+    /// Not set for levels in separate files
+    layer_instances: Option<Vec<LayerInstance>>,
+}
+
+impl LdtkLevel {
+    fn bounds(&self) -> LevelBounds {
+        LevelBounds {
+            min: Vec2::new(self.world_x, -self.world_y - self.px_hei),
+            max: Vec2::new(self.world_x + self.px_wid, -self.world_y),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NeighbourLink {
+    level_iid: String,
+    dir: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__type")]
+    kind: String,
+    #[serde(rename = "__cWid")]
+    columns: usize,
+    #[serde(rename = "__cHei")]
+    rows: usize,
+    #[serde(rename = "__gridSize")]
+    grid_size: f32,
+    #[serde(rename = "__pxTotalOffsetX", default)]
+    offset_x: f32,
+    #[serde(rename = "__pxTotalOffsetY", default)]
+    offset_y: f32,
+    #[serde(rename = "__tilesetDefUid")]
+    tileset_def_uid: Option<i64>,
+    layer_def_uid: i64,
+    #[serde(default = "LayerInstance::default_visible")]
+    visible: bool,
+    #[serde(default)]
+    int_grid_csv: Vec<i64>,
+    #[serde(default)]
+    grid_tiles: Vec<TileInstance>,
+    #[serde(default)]
+    auto_layer_tiles: Vec<TileInstance>,
+    #[serde(default)]
+    entity_instances: Vec<EntityInstance>,
+}
+
+impl LayerInstance {
+    fn default_visible() -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize)]
+struct TileInstance {
+    /// Top left corner in the layer
+    px: [f32; 2],
+    /// Index of the tile in the tileset
+    t: usize,
+    /// Flip bits, x then y
+    f: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    iid: String,
+    /// Position of the pivot in the layer
+    px: [f32; 2],
+    /// Fraction of the size from the top left corner to the pivot
+    #[serde(rename = "__pivot")]
+    pivot: [f32; 2],
+    width: f32,
+    height: f32,
+    #[serde(default)]
+    field_instances: Vec<FieldInstance>,
+}
+
+#[derive(Deserialize)]
+struct FieldInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__value")]
+    value: Value,
+}
+
+/// What an IntGrid value turns into
+#[derive(Clone, Copy, PartialEq)]
+enum IntGridKind {
+    Ground(GroundMaterialKind),
+    OneWay,
+    Hazard(HazardKind),
+}
+
+impl IntGridKind {
+    fn from_identifier(identifier: &str) -> Option<Self> {
+        match identifier.to_lowercase().as_str() {
+            "ground" | "solid" | "wall" => Some(Self::Ground(GroundMaterialKind::Normal)),
+            "ice" => Some(Self::Ground(GroundMaterialKind::Ice)),
+            "mud" => Some(Self::Ground(GroundMaterialKind::Mud)),
+            "sticky" => Some(Self::Ground(GroundMaterialKind::Sticky)),
+            "one_way" | "oneway" | "platform" => Some(Self::OneWay),
+            "spikes" => Some(Self::Hazard(HazardKind::Spikes)),
+            "lava" => Some(Self::Hazard(HazardKind::Lava)),
+            _ => None,
+        }
+    }
+}
+
+struct Import<'a> {
+    project: &'a LdtkProject,
+    source: &'a LdtkLevel,
+    /// Folder of the project
+    dir: &'a Path,
+    /// Where the top left corner of the level is in the world
+    origin: Vec2,
+    /// Index in the level's tilesets of every tileset used so far, by uid
+    tilesets: HashMap<i64, usize>,
+    level: LevelData,
+}
+
+impl Import<'_> {
+    /// Converts a position in the level, where y points down from the top, to the world
+    fn point(&self, x: f32, y: f32) -> Vec2 {
+        Vec2::new(self.origin.x + x, self.origin.y - y)
+    }
+
+    fn error(&self, message: impl Into<String>) -> LdtkError {
+        LdtkError::Level {
+            level: self.source.identifier.clone(),
+            message: message.into(),
+        }
+    }
+
+    fn layer(&mut self, layer: &LayerInstance, entities: &LdtkEntities) -> Result<(), LdtkError> {
+        if layer.offset_x != 0f32 || layer.offset_y != 0f32 {
+            return Err(self.error(format!(
+                "layer `{}` has an offset, which is not supported",
+                layer.identifier
+            )));
+        }
+
+        match layer.kind.as_str() {
+            "IntGrid" => {
+                self.int_grid(layer)?;
+                self.tiles(layer, &layer.auto_layer_tiles)
+            }
+            "AutoLayer" => self.tiles(layer, &layer.auto_layer_tiles),
+            "Tiles" => self.tiles(layer, &layer.grid_tiles),
+            "Entities" => {
+                for entity in layer.entity_instances.iter() {
+                    self.entity(entity, entities)?;
+                }
+                Ok(())
+            }
+            kind => Err(self.error(format!(
+                "layer `{}` is of unknown type {kind}",
+                layer.identifier
+            ))),
+        }
+    }
+
+    fn int_grid(&mut self, layer: &LayerInstance) -> Result<(), LdtkError> {
+        let name = &layer.identifier;
+        let values = &layer.int_grid_csv;
+        if values.len() != layer.columns * layer.rows {
+            return Err(self.error(format!(
+                "IntGrid layer `{name}` has {} cells instead of {}",
+                values.len(),
+                layer.columns * layer.rows
+            )));
+        }
+        let definition = self
+            .project
+            .defs
+            .layers
+            .iter()
+            .find(|definition| definition.uid == layer.layer_def_uid)
+            .ok_or_else(|| self.error(format!("layer `{name}` has no definition")))?;
+
+        // Cells of every kind, in order of appearance
+        let mut kinds: Vec<(IntGridKind, Vec<bool>)> = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            if *value == 0 {
+                continue;
+            }
+            let identifier = definition
+                .int_grid_values
+                .iter()
+                .find(|definition| definition.value == *value)
+                .and_then(|definition| definition.identifier.as_deref())
+                .unwrap_or_default();
+            let kind = IntGridKind::from_identifier(identifier).ok_or_else(|| {
+                self.error(format!(
+                    "IntGrid layer `{name}` has value {value} `{identifier}`, \
+                    expected {INT_GRID_VALUES}"
+                ))
+            })?;
+
+            let index = match kinds.iter().position(|(other, _)| *other == kind) {
+                Some(index) => index,
+                None => {
+                    kinds.push((kind, vec![false; values.len()]));
+                    kinds.len() - 1
+                }
+            };
+            kinds[index].1[i] = true;
+        }
+
+        // Auto-layer tiles are drawn instead of the grounds
+        let color = (layer.visible && !layer.auto_layer_tiles.is_empty()).then_some(Color::NONE);
+        let mut count = 0;
+        for (kind, cells) in kinds {
+            for (col, row, w, h) in merge_cells(&cells, layer.columns) {
+                count += 1;
+                let size = Vec2::new(w as f32, h as f32) * layer.grid_size;
+                let corner = Vec2::new(col as f32, row as f32) * layer.grid_size;
+                let position = self.point(corner.x + size.x / 2f32, corner.y + size.y / 2f32);
+                let name = format!("{name} {count}");
+
+                match kind {
+                    IntGridKind::Ground(material) => self.level.grounds.push(GroundData {
+                        name,
+                        position,
+                        size,
+                        material,
+                        color,
+                        one_way: false,
+                        path: None,
+                    }),
+                    IntGridKind::OneWay => self.level.grounds.push(GroundData {
+                        name,
+                        position,
+                        size,
+                        material: GroundMaterialKind::Normal,
+                        color,
+                        one_way: true,
+                        path: None,
+                    }),
+                    IntGridKind::Hazard(kind) => self.level.hazards.push(HazardData {
+                        name,
+                        position,
+                        size,
+                        kind,
+                        damage: None,
+                    }),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn tiles(&mut self, layer: &LayerInstance, tiles: &[TileInstance]) -> Result<(), LdtkError> {
+        if !layer.visible || tiles.is_empty() {
+            return Ok(());
+        }
+        let Some(uid) = layer.tileset_def_uid else {
+            return Err(self.error(format!(
+                "layer `{}` has tiles but no tileset",
+                layer.identifier
+            )));
+        };
+
+        let tileset = self.tileset(uid)?;
+        let TilesetData {
+            tile_size,
+            columns,
+            rows,
+            ..
+        } = self.level.tilesets[tileset];
+        for tile in tiles {
+            if tile.t >= columns * rows {
+                return Err(self.error(format!(
+                    "layer `{}` has tile {} at {}, {}, which is past the end of its tileset",
+                    layer.identifier, tile.t, tile.px[0], tile.px[1]
+                )));
+            }
+            self.level.tiles.push(TileData {
+                tileset,
+                index: tile.t,
+                position: self.point(
+                    tile.px[0] + tile_size.x / 2f32,
+                    tile.px[1] + tile_size.y / 2f32,
+                ),
+                flip_x: tile.f & 1 != 0,
+                flip_y: tile.f & 2 != 0,
+            });
+        }
+        Ok(())
+    }
+
+    /// Adds the tileset to the level the first time it is used
+    fn tileset(&mut self, uid: i64) -> Result<usize, LdtkError> {
+        if let Some(index) = self.tilesets.get(&uid) {
+            return Ok(*index);
+        }
+
+        let Some(tileset) = self.project.defs.tilesets.iter().find(|t| t.uid == uid) else {
+            return Err(self.error(format!("tileset {uid} has no definition")));
+        };
+        let Some(image) = &tileset.rel_path else {
+            return Err(self.error(format!("tileset `{}` has no image", tileset.identifier)));
+        };
+        let image = asset_path(self.dir, image).ok_or_else(|| {
+            self.error(format!(
+                "image `{image}` of tileset `{}` is outside the assets folder",
+                tileset.identifier
+            ))
+        })?;
+
+        self.level.tilesets.push(TilesetData {
+            image,
+            tile_size: Vec2::splat(tileset.tile_grid_size),
+            columns: tileset.columns,
+            rows: tileset.rows,
+            spacing: tileset.spacing,
+            margin: tileset.padding,
+        });
+        let index = self.level.tilesets.len() - 1;
+        self.tilesets.insert(uid, index);
+        Ok(index)
+    }
+
+    fn entity(
+        &mut self,
+        entity: &EntityInstance,
+        entities: &LdtkEntities,
+    ) -> Result<(), LdtkError> {
+        let size = Vec2::new(entity.width, entity.height);
+        let corner = Vec2::from(entity.px) - Vec2::from(entity.pivot) * size;
+        let instance = LdtkEntityInstance {
+            identifier: entity.identifier.clone(),
+            iid: entity.iid.clone(),
+            position: self.point(corner.x + size.x / 2f32, corner.y + size.y / 2f32),
+            size,
+            fields: entity
+                .field_instances
+                .iter()
+                .map(|field| (field.identifier.clone(), field.value.clone()))
+                .collect(),
+        };
+
+        let Some(import) = entities.importers.get(&entity.identifier) else {
+            return Err(self.error(format!(
+                "entity `{}` ({}) is not registered as anything",
+                entity.identifier, entity.iid
+            )));
+        };
+        import(&instance, &mut self.level).map_err(|message| {
+            self.error(format!(
+                "entity `{}` ({}): {message}",
+                entity.identifier, entity.iid
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "assets/levels/ldtk/example.ldtk";
+
+    fn import_example(level: &str, entities: &LdtkEntities) -> Result<LevelData, LdtkError> {
+        import(Path::new(EXAMPLE), Some(level), entities)
+    }
+
+    #[test]
+    fn int_grid_values_become_grounds_and_hazards() {
+        let level = import_example("Level_0", &LdtkEntities::default()).unwrap();
+
+        // The floor is split around the spikes into three grounds, plus the one-way platform
+        assert_eq!(level.grounds.len(), 4);
+        let one_way = level
+            .grounds
+            .iter()
+            .filter(|ground| ground.one_way)
+            .collect::<Vec<_>>();
+        assert_eq!(one_way.len(), 1);
+        assert_eq!(one_way[0].size, Vec2::new(100f32, 25f32));
+        assert_eq!(one_way[0].position, Vec2::new(150f32, -187.5f32));
+        assert!(level
+            .grounds
+            .iter()
+            .all(|ground| ground.material == GroundMaterialKind::Normal));
+
+        assert_eq!(level.hazards.len(), 1);
+        assert_eq!(level.hazards[0].kind, HazardKind::Spikes);
+        assert_eq!(level.hazards[0].size, Vec2::new(50f32, 25f32));
+        assert_eq!(level.hazards[0].position, Vec2::new(325f32, -262.5f32));
+
+        let level = import_example("Level_1", &LdtkEntities::default()).unwrap();
+        assert_eq!(level.hazards.len(), 1);
+        assert_eq!(level.hazards[0].kind, HazardKind::Lava);
+    }
+
+    #[test]
+    fn entities_are_imported_as_registered() {
+        let level = import_example("Level_0", &LdtkEntities::default()).unwrap();
+        assert_eq!(level.spawn, Vec2::new(50f32, -225f32));
+        assert_eq!(level.collectibles.len(), 1);
+        assert_eq!(level.checkpoints.len(), 1);
+
+        let mut app = App::new();
+        app.register_ldtk_entity::<Checkpoint>("Coin");
+        let level = import_example("Level_0", app.world.resource::<LdtkEntities>()).unwrap();
+        assert!(level.collectibles.is_empty());
+        assert_eq!(level.checkpoints.len(), 2);
+    }
+
+    #[test]
+    fn unregistered_entities_are_rejected() {
+        let mut entities = LdtkEntities::empty();
+        entities
+            .register::<SpawnPoint>("Spawn")
+            .register::<Checkpoint>("Checkpoint");
+
+        let err = import_example("Level_0", &entities).unwrap_err();
+        assert!(matches!(&err, LdtkError::Level { level, .. } if level == "Level_0"));
+        assert!(err.to_string().contains("`Coin`"), "{err}");
+    }
+
+    #[test]
+    fn neighbours_are_linked() {
+        let level = import_example("Level_0", &LdtkEntities::default()).unwrap();
+
+        assert_eq!(
+            level.bounds,
+            Some(LevelBounds {
+                min: Vec2::new(0f32, -300f32),
+                max: Vec2::new(500f32, 0f32),
+            })
+        );
+        assert_eq!(
+            level.neighbours,
+            vec![LevelNeighbour {
+                level: format!("{EXAMPLE}#Level_1"),
+                bounds: LevelBounds {
+                    min: Vec2::new(500f32, -300f32),
+                    max: Vec2::new(1000f32, 0f32),
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn tiles_past_the_tileset_are_rejected() {
+        let err = import(
+            Path::new("tests/fixtures/ldtk/tile_past_the_tileset.ldtk"),
+            Some("Level_0"),
+            &LdtkEntities::empty(),
+        )
+        .unwrap_err();
+        assert!(matches!(&err, LdtkError::Level { level, .. } if level == "Level_0"));
+        assert!(err.to_string().contains("`Decoration`"), "{err}");
+    }
+}
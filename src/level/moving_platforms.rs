@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::LevelState;
+use crate::{
+    menu::AppState,
+    player::movement::{sub_components::Surface, CharacterController},
+};
+
+pub(super) struct MovingPlatformsPlugin;
+
+impl Plugin for MovingPlatformsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (move_platforms, carry_riders)
+                .chain()
+                .run_if(in_state(LevelState::Playing))
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Route of a moving ground
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GroundPath {
+    /// Points the center of the ground moves through after its position, relative to it. Once at
+    /// the last point, the ground goes back the same way
+    pub points: Vec<Vec2>,
+    /// Pixels per second
+    pub speed: f32,
+}
+
+impl GroundPath {
+    /// Every point of the route, starting at the ground's position
+    fn route(&self) -> impl Iterator<Item = Vec2> + '_ {
+        std::iter::once(Vec2::ZERO).chain(self.points.iter().copied())
+    }
+
+    pub fn length(&self) -> f32 {
+        self.route()
+            .zip(self.route().skip(1))
+            .map(|(from, to)| from.distance(to))
+            .sum()
+    }
+
+    /// Offset from the ground's position `time` seconds after it started moving
+    pub fn offset_at(&self, time: f32) -> Vec2 {
+        let length = self.length();
+        if length <= 0f32 || self.speed <= 0f32 {
+            return Vec2::ZERO;
+        }
+
+        // There and back again
+        let mut distance = (time * self.speed) % (2f32 * length);
+        if distance > length {
+            distance = 2f32 * length - distance;
+        }
+
+        for (from, to) in self.route().zip(self.route().skip(1)) {
+            let segment = from.distance(to);
+            if distance <= segment && segment > 0f32 {
+                return from.lerp(to, distance / segment);
+            }
+            distance -= segment;
+        }
+        self.points.last().copied().unwrap_or_default()
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct MovingPlatform {
+    pub path: GroundPath,
+    /// Position the path is relative to
+    pub origin: Vec2,
+    pub time: f32,
+    /// How far the platform moved on the last frame
+    pub delta: Vec2,
+}
+
+impl MovingPlatform {
+    pub fn new(origin: Vec2, path: GroundPath) -> Self {
+        Self {
+            path,
+            origin,
+            time: 0f32,
+            delta: Vec2::ZERO,
+        }
+    }
+}
+
+fn move_platforms(
+    mut platform_query: Query<(&mut MovingPlatform, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (mut platform, mut transform) in platform_query.iter_mut() {
+        platform.time += time.delta_seconds();
+        let position = platform.origin + platform.path.offset_at(platform.time);
+
+        platform.delta = position - transform.translation.truncate();
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Moves characters standing on moving platforms along with them
+fn carry_riders(
+    platform_query: Query<&MovingPlatform>,
+    mut rider_query: Query<(&CharacterController, &mut Transform), Without<MovingPlatform>>,
+) {
+    for (controller, mut transform) in rider_query.iter_mut() {
+        let Some(contact) = controller.surface_checker.contact(&Surface::Bottom) else {
+            continue;
+        };
+        let Ok(platform) = platform_query.get(contact.entity) else {
+            continue;
+        };
+
+        transform.translation += platform.delta.extend(0f32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path() -> GroundPath {
+        GroundPath {
+            points: vec![Vec2::new(100f32, 0f32), Vec2::new(100f32, 50f32)],
+            speed: 50f32,
+        }
+    }
+
+    #[test]
+    fn moves_along_the_path_and_back() {
+        let path = path();
+        assert_eq!(path.length(), 150f32);
+
+        assert_eq!(path.offset_at(0f32), Vec2::ZERO);
+        assert_eq!(path.offset_at(1f32), Vec2::new(50f32, 0f32));
+        assert_eq!(path.offset_at(2.5f32), Vec2::new(100f32, 25f32));
+        assert_eq!(path.offset_at(3f32), Vec2::new(100f32, 50f32));
+        // On the way back
+        assert_eq!(path.offset_at(4f32), Vec2::new(100f32, 0f32));
+        assert_eq!(path.offset_at(6f32), Vec2::ZERO);
+        assert_eq!(path.offset_at(7f32), Vec2::new(50f32, 0f32));
+    }
+
+    #[test]
+    fn skips_repeated_points() {
+        let path = GroundPath {
+            points: vec![Vec2::ZERO, Vec2::new(0f32, 100f32), Vec2::new(0f32, 100f32)],
+            speed: 100f32,
+        };
+
+        assert_eq!(path.offset_at(0.5f32), Vec2::new(0f32, 50f32));
+        assert_eq!(path.offset_at(1f32), Vec2::new(0f32, 100f32));
+    }
+}
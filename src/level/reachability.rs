@@ -0,0 +1,574 @@
+use bevy::prelude::*;
+use std::{collections::VecDeque, fmt};
+
+use super::{
+    expand_level_paths, Checkpoint, LevelData, LevelGoal, LevelSequence, LEVEL_SEQUENCE_PATH,
+};
+use crate::{
+    player::{
+        health::KILL_DEPTH,
+        movement::{preset::read_player_preset, CharacterControllerBuilder, TAKEOFF_SPEED_BOOST},
+    },
+    GRAVITY,
+};
+
+/// Length of a simulation step, the same as a physics step at 60 fps
+const STEP: f32 = 1f32 / 60f32;
+/// Jumps still in the air after this long are given up on
+const MAX_AIR_TIME: f32 = 10f32;
+/// Distance between the takeoff points tried along every ground
+const TAKEOFF_SPACING: f32 = 10f32;
+/// Fractions of the top running speed every jump is tried with
+const SPEED_FRACTIONS: [f32; 5] = [0f32, 0.25f32, 0.5f32, 0.75f32, 1f32];
+/// Input held in the air for every jump, steering left, right or not at all
+const STEERING: [f32; 3] = [-1f32, 0f32, 1f32];
+
+/// Validates the levels at `paths`, or every level of the sequence if there are none, against the
+/// player's preset. Prints what can't be reached and returns whether every level passed
+pub fn validate_levels(paths: &[String]) -> bool {
+    let controller = read_player_preset();
+
+    let paths = if paths.is_empty() {
+        match LevelSequence::load(LEVEL_SEQUENCE_PATH) {
+            Ok(sequence) => sequence.levels,
+            Err(err) => {
+                println!("Could not load level sequence {LEVEL_SEQUENCE_PATH}. {err}");
+                return false;
+            }
+        }
+    } else {
+        match expand_level_paths(paths) {
+            Ok(paths) => paths,
+            Err(err) => {
+                println!("{err}");
+                return false;
+            }
+        }
+    };
+
+    let mut passed = true;
+    for path in paths {
+        let level = match LevelData::load(&path) {
+            Ok(level) => level,
+            Err(err) => {
+                println!("{path}: {err}");
+                passed = false;
+                continue;
+            }
+        };
+
+        let report = ReachabilityReport::new(&level, &controller);
+        if report.is_ok() {
+            println!("{path}: everything can be reached");
+        } else {
+            println!("{path}:\n{report}");
+            passed = false;
+        }
+    }
+    passed
+}
+
+/// What can't be reached from the spawn point of a level. Jumps are simulated from every part of a
+/// ground the player can land on, steering one way for the whole jump and without wall jumps, so
+/// some levels that need them are reported even though they can be finished. Hazards on a ground
+/// split it into parts that can't be walked between. Moving platforms are only stood on where they
+/// start
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReachabilityReport {
+    /// The player falls out of the level from the spawn point
+    pub spawn_over_nothing: bool,
+    /// Names of grounds wider than they are tall that are never landed on. Grounds with hazards
+    /// all along their top are left out
+    pub unreachable_platforms: Vec<String>,
+    pub unreachable_collectibles: Vec<String>,
+    pub unreachable_checkpoints: Vec<Vec2>,
+    pub unreachable_goal: bool,
+}
+
+impl ReachabilityReport {
+    pub fn new(level: &LevelData, controller: &CharacterControllerBuilder) -> Self {
+        let simulation = Simulation::new(level, controller);
+        let mut touched = vec![false; simulation.targets.len()];
+        let mut reached = vec![false; simulation.spans.len()];
+
+        let mut queue = VecDeque::new();
+        let spawn_span = simulation.fly(level.spawn, Vec2::ZERO, 0f32, &mut touched);
+        if let Some(span) = spawn_span {
+            reached[span] = true;
+            queue.push_back(span);
+        }
+        while let Some(span) = queue.pop_front() {
+            for landed in simulation.explore(span, &mut touched) {
+                if !reached[landed] {
+                    reached[landed] = true;
+                    queue.push_back(landed);
+                }
+            }
+        }
+
+        // Grounds covered by hazards have no spans, and can't be stood on anyway
+        let mut ground_reached = vec![None; level.grounds.len()];
+        for (span, reached) in simulation.spans.iter().zip(reached) {
+            let ground = &mut ground_reached[span.ground];
+            *ground = Some(ground.unwrap_or(false) || reached);
+        }
+
+        let mut touched = touched.into_iter();
+        Self {
+            spawn_over_nothing: spawn_span.is_none(),
+            unreachable_platforms: level
+                .grounds
+                .iter()
+                .zip(ground_reached)
+                .filter(|(ground, reached)| {
+                    ground.size.x > ground.size.y && *reached == Some(false)
+                })
+                .map(|(ground, _)| ground.name.clone())
+                .collect(),
+            unreachable_collectibles: level
+                .collectibles
+                .iter()
+                .zip(touched.by_ref())
+                .filter(|(_, touched)| !touched)
+                .map(|(collectible, _)| collectible.name.clone())
+                .collect(),
+            unreachable_checkpoints: level
+                .checkpoints
+                .iter()
+                .zip(touched.by_ref())
+                .filter(|(_, touched)| !touched)
+                .map(|(checkpoint, _)| *checkpoint)
+                .collect(),
+            unreachable_goal: level.goal.is_some() && touched.next() == Some(false),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for ReachabilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.spawn_over_nothing {
+            writeln!(
+                f,
+                "    the player falls out of the level from the spawn point"
+            )?;
+        }
+        for platform in &self.unreachable_platforms {
+            writeln!(f, "    platform `{platform}` can't be reached")?;
+        }
+        for collectible in &self.unreachable_collectibles {
+            writeln!(f, "    collectible `{collectible}` can't be reached")?;
+        }
+        for checkpoint in &self.unreachable_checkpoints {
+            writeln!(f, "    checkpoint at {checkpoint} can't be reached")?;
+        }
+        if self.unreachable_goal {
+            writeln!(f, "    the goal can't be reached")?;
+        }
+        Ok(())
+    }
+}
+
+/// Part of the top of a ground the character can stand on without touching a hazard, as the range
+/// of x positions of the character's center
+struct Span {
+    ground: usize,
+    min: f32,
+    max: f32,
+}
+
+/// The level as rectangles, and the movement of the character through it
+struct Simulation<'a> {
+    level: &'a LevelData,
+    controller: &'a CharacterControllerBuilder,
+    gravity: f32,
+    kill_height: f32,
+
+    grounds: Vec<Rect>,
+    /// Whether each ground is a one-way platform
+    one_way: Vec<bool>,
+    hazards: Vec<Rect>,
+    /// Collectibles, then checkpoints, then the goal
+    targets: Vec<Rect>,
+    spans: Vec<Span>,
+}
+
+impl<'a> Simulation<'a> {
+    fn new(level: &'a LevelData, controller: &'a CharacterControllerBuilder) -> Self {
+        let targets = level
+            .collectibles
+            .iter()
+            .map(|collectible| {
+                Rect::from_center_size(collectible.position, collectible.kind.size())
+            })
+            .chain(
+                level
+                    .checkpoints
+                    .iter()
+                    .map(|checkpoint| Rect::from_center_size(*checkpoint, Checkpoint::SIZE)),
+            )
+            .chain(
+                level
+                    .goal
+                    .map(|goal| Rect::from_center_size(goal, LevelGoal::SIZE)),
+            )
+            .collect();
+        let grounds = level
+            .grounds
+            .iter()
+            .map(|ground| Rect::from_center_size(ground.position, ground.size))
+            .collect::<Vec<_>>();
+        let hazards = level
+            .hazards
+            .iter()
+            .map(|hazard| Rect::from_center_size(hazard.position, hazard.size))
+            .collect::<Vec<_>>();
+
+        Self {
+            level,
+            controller,
+            gravity: GRAVITY * controller.gravity_scale,
+            kill_height: level.bounds.map_or(0f32, |bounds| bounds.min.y) - KILL_DEPTH,
+            one_way: level.grounds.iter().map(|ground| ground.one_way).collect(),
+            spans: spans(&grounds, &hazards, controller.size),
+            grounds,
+            hazards,
+            targets,
+        }
+    }
+
+    fn span_at(&self, ground: usize, x: f32) -> Option<usize> {
+        self.spans
+            .iter()
+            .position(|span| span.ground == ground && span.min <= x && x <= span.max)
+    }
+
+    fn body(&self, position: Vec2) -> Rect {
+        Rect::from_center_size(position, self.controller.size)
+    }
+
+    /// A ground the body overlaps, leaving out one-way platforms unless the body was above them
+    fn blocking_ground(&self, body: Rect, previous_bottom: Option<f32>) -> Option<usize> {
+        self.grounds
+            .iter()
+            .zip(&self.one_way)
+            .position(|(ground, one_way)| {
+                !ground.intersect(body).is_empty()
+                    && (!one_way || previous_bottom.is_some_and(|bottom| bottom >= ground.max.y))
+            })
+    }
+
+    fn in_hazard(&self, body: Rect) -> bool {
+        self.hazards
+            .iter()
+            .any(|hazard| !hazard.intersect(body).is_empty())
+    }
+
+    fn touch(&self, body: Rect, touched: &mut [bool]) {
+        for (target, touched) in self.targets.iter().zip(touched) {
+            *touched |= !target.intersect(body).is_empty();
+        }
+    }
+
+    /// Moves the character through the air while holding `steer` on the run axis, until it lands.
+    /// Returns the span it landed on. Touching a hazard or falling out of the level ends the jump
+    /// without landing
+    fn fly(
+        &self,
+        mut position: Vec2,
+        mut velocity: Vec2,
+        steer: f32,
+        touched: &mut [bool],
+    ) -> Option<usize> {
+        let max_speed = self.controller.max_move_speed;
+        let mut time = 0f32;
+        while time < MAX_AIR_TIME && position.y > self.kill_height {
+            time += STEP;
+            velocity.y -= self.gravity * STEP;
+
+            // Air control, the same as horizontal movement off the ground
+            if steer != 0f32 {
+                let turnaround = if steer != velocity.x.signum() {
+                    self.controller.turnaround_multi
+                } else {
+                    1f32
+                };
+                let acceleration =
+                    self.controller.acceleration_force * self.controller.air_control * turnaround;
+                velocity.x =
+                    (velocity.x + steer * acceleration * STEP).clamp(-max_speed, max_speed);
+            }
+
+            let moved = position + Vec2::X * velocity.x * STEP;
+            if self.blocking_ground(self.body(moved), None).is_none() {
+                position = moved;
+            } else {
+                velocity.x = 0f32;
+            }
+
+            let moved = position + Vec2::Y * velocity.y * STEP;
+            let falling = (velocity.y <= 0f32).then_some(self.body(position).min.y);
+            match self.blocking_ground(self.body(moved), falling) {
+                None => position = moved,
+                // Landing next to a hazard is landing in it
+                Some(ground) if velocity.y <= 0f32 => return self.span_at(ground, position.x),
+                Some(_) => velocity.y = 0f32,
+            }
+
+            self.touch(self.body(position), touched);
+            if self.in_hazard(self.body(position)) {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Every span that can be landed on by jumping or walking off `span`
+    fn explore(&self, span: usize, touched: &mut [bool]) -> Vec<usize> {
+        let Span { ground, min, max } = self.spans[span];
+        let top = self.grounds[ground];
+        let material = self.level.grounds[ground].material.material();
+        let size = self.controller.size;
+        // Just above the ground, so the body doesn't overlap it
+        let height = top.max.y + size.y / 2f32 + 0.1f32;
+
+        // The character still stands on the ground while half of it hangs over the edge
+        let overhang = size.x / 2f32 - 0.1f32;
+        let left = min.max(top.min.x - overhang);
+        let right = max.min(top.max.x + overhang);
+
+        // Walking along the top
+        self.touch(
+            Rect::new(
+                left - size.x / 2f32,
+                top.max.y,
+                right + size.x / 2f32,
+                top.max.y + size.y,
+            ),
+            touched,
+        );
+
+        let speed = self.controller.max_move_speed * material.max_speed_multi;
+        let jump = self.controller.jump_force
+            * material.jump_force_multi
+            * material.bounce.unwrap_or(1f32);
+
+        let mut takeoffs = Vec::new();
+        let mut x = left;
+        while x < right {
+            takeoffs.push((Vec2::new(x, height), jump));
+            x += TAKEOFF_SPACING;
+        }
+        takeoffs.push((Vec2::new(right, height), jump));
+        // Bouncy grounds jump as soon as they are landed on, so they can't be walked off. Ends of
+        // the span at a hazard can't be walked past either
+        if material.bounce.is_none() {
+            let edge = size.x / 2f32 + 0.1f32;
+            if min < left {
+                takeoffs.push((Vec2::new(top.min.x - edge, height), 0f32));
+            }
+            if max > right {
+                takeoffs.push((Vec2::new(top.max.x + edge, height), 0f32));
+            }
+        }
+
+        let mut landed = Vec::new();
+        for (position, jump) in takeoffs {
+            let body = self.body(position);
+            if self.blocking_ground(body, None).is_some() || self.in_hazard(body) {
+                continue;
+            }
+
+            for direction in [-1f32, 1f32] {
+                for fraction in SPEED_FRACTIONS {
+                    let mut velocity = Vec2::new(direction * fraction * speed, jump);
+                    if jump > 0f32 {
+                        velocity.x *= TAKEOFF_SPEED_BOOST;
+                    }
+                    for steer in STEERING {
+                        landed.extend(self.fly(position, velocity, steer, touched));
+                    }
+                }
+            }
+        }
+        landed
+    }
+}
+
+/// The parts of the top of every ground that aren't next to a hazard. Spans reach as far as the
+/// character still overlaps the ground
+fn spans(grounds: &[Rect], hazards: &[Rect], size: Vec2) -> Vec<Span> {
+    let mut spans = Vec::new();
+    for (ground, top) in grounds.iter().enumerate() {
+        let min = top.min.x - size.x / 2f32;
+        let max = top.max.x + size.x / 2f32;
+        let strip = Rect::new(min, top.max.y, max, top.max.y + size.y);
+
+        // Where the character's center can't be without touching a hazard
+        let mut blocked = hazards
+            .iter()
+            .filter(|hazard| !hazard.intersect(strip).is_empty())
+            .map(|hazard| (hazard.min.x - size.x / 2f32, hazard.max.x + size.x / 2f32))
+            .collect::<Vec<_>>();
+        blocked.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut start = min;
+        for (from, to) in blocked {
+            if from > start {
+                spans.push(Span {
+                    ground,
+                    min: start,
+                    max: from.min(max),
+                });
+            }
+            start = start.max(to);
+        }
+        if start < max {
+            spans.push(Span {
+                ground,
+                min: start,
+                max,
+            });
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::{
+        collectibles::{CollectibleData, CollectibleKind},
+        GroundData, GroundMaterialKind, HazardData, HazardKind,
+    };
+
+    fn ground(name: &str, position: Vec2, size: Vec2) -> GroundData {
+        GroundData {
+            name: name.to_string(),
+            position,
+            size,
+            material: GroundMaterialKind::Normal,
+            color: None,
+            one_way: false,
+            path: None,
+        }
+    }
+
+    fn coin(name: &str, position: Vec2) -> CollectibleData {
+        CollectibleData {
+            name: name.to_string(),
+            position,
+            kind: CollectibleKind::Coin,
+        }
+    }
+
+    /// A 400 pixel wide floor with its top at y = 0, and the spawn point above its middle
+    fn level(grounds: Vec<GroundData>) -> LevelData {
+        let mut grounds = grounds;
+        grounds.insert(
+            0,
+            ground("Floor", Vec2::new(0f32, -25f32), Vec2::new(400f32, 50f32)),
+        );
+        LevelData {
+            spawn: Vec2::new(0f32, 50f32),
+            grounds,
+            ..Default::default()
+        }
+    }
+
+    fn report(level: &LevelData) -> ReachabilityReport {
+        ReachabilityReport::new(level, &CharacterControllerBuilder::default())
+    }
+
+    fn arc() -> crate::player::movement::jump_arc::JumpArc {
+        CharacterControllerBuilder::default().jump_arc()
+    }
+
+    #[test]
+    fn platform_above_the_jump_is_unreachable() {
+        let size = Vec2::new(100f32, 20f32);
+        let low = level(vec![ground(
+            "Ledge",
+            Vec2::new(150f32, arc().height - 20f32 - size.y / 2f32),
+            size,
+        )]);
+        assert!(report(&low).is_ok(), "{}", report(&low));
+
+        let high = level(vec![ground(
+            "Ledge",
+            Vec2::new(150f32, arc().height + 20f32 - size.y / 2f32),
+            size,
+        )]);
+        assert_eq!(report(&high).unreachable_platforms, vec!["Ledge"]);
+    }
+
+    #[test]
+    fn gap_wider_than_the_jump_is_unreachable() {
+        let far_side = |gap: f32| {
+            level(vec![ground(
+                "Far side",
+                Vec2::new(200f32 + gap + 200f32, -25f32),
+                Vec2::new(400f32, 50f32),
+            )])
+        };
+
+        let narrow = far_side(arc().distance / 2f32);
+        assert!(report(&narrow).is_ok(), "{}", report(&narrow));
+
+        let wide = far_side(arc().distance * 2f32);
+        assert_eq!(report(&wide).unreachable_platforms, vec!["Far side"]);
+    }
+
+    #[test]
+    fn collectible_behind_a_hazard_is_unreachable() {
+        let mut level = level(Vec::new());
+        level.collectibles = vec![coin("Behind spikes", Vec2::new(175f32, 25f32))];
+        assert!(report(&level).is_ok(), "{}", report(&level));
+
+        // Too tall to jump over, with nothing to stand on behind it
+        let wall = Vec2::new(20f32, arc().height * 3f32);
+        level.hazards = vec![HazardData {
+            name: "Spike wall".to_string(),
+            position: Vec2::new(120f32, wall.y / 2f32),
+            size: wall,
+            kind: HazardKind::Spikes,
+            damage: None,
+        }];
+        assert_eq!(
+            report(&level).unreachable_collectibles,
+            vec!["Behind spikes"]
+        );
+    }
+
+    #[test]
+    fn spawn_over_nothing_is_reported() {
+        let mut level = level(Vec::new());
+        level.spawn = Vec2::new(1000f32, 50f32);
+
+        let report = report(&level);
+        assert!(report.spawn_over_nothing);
+        assert_eq!(report.unreachable_platforms, vec!["Floor"]);
+    }
+
+    #[test]
+    fn one_way_platform_is_reached_from_below() {
+        // Wider than the floor, so it can only be reached through it, and just above the spawned
+        // player
+        let mut platform = ground("Platform", Vec2::new(0f32, 65f32), Vec2::new(600f32, 10f32));
+        let coin_height = 70f32 + arc().height * 0.6f32;
+
+        let mut level = level(vec![platform.clone()]);
+        level.spawn.y = 26f32;
+        level.collectibles = vec![coin("On top", Vec2::new(0f32, coin_height))];
+        let report_solid = report(&level);
+        assert_eq!(report_solid.unreachable_platforms, vec!["Platform"]);
+        assert_eq!(report_solid.unreachable_collectibles, vec!["On top"]);
+
+        platform.one_way = true;
+        level.grounds[1] = platform;
+        assert!(report(&level).is_ok(), "{}", report(&level));
+    }
+}
@@ -0,0 +1,775 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+use super::{
+    collectibles::{CollectibleData, CollectibleKind},
+    import::{asset_path, merge_cells, parse_hazard, parse_material},
+    moving_platforms::GroundPath,
+    resolve_path,
+    tiles::{TileData, TilesetData},
+    GroundData, GroundMaterialKind, HazardData, LevelData,
+};
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+/// Object types that become level entities
+const OBJECT_TYPES: &str = "spawn, checkpoint, goal, ground, moving_platform, hazard, coin or gem";
+
+const GID_MASK: u32 =
+    !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL);
+
+#[derive(Debug)]
+pub enum TiledError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    /// Something about the map as a whole that can't be imported
+    Map(String),
+    /// An object that can't be turned into a level entity
+    Object {
+        id: u32,
+        message: String,
+    },
+}
+
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TiledError::Io(err) => write!(f, "Could not read map: {err}"),
+            TiledError::Parse(err) => write!(f, "Invalid map: {err}"),
+            TiledError::Map(message) => write!(f, "{message}"),
+            TiledError::Object { id, message } => write!(f, "object {id}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for TiledError {}
+
+/// Imports a Tiled JSON map as a level.
+///
+/// Tile layers become sprite tiles, and their tiles are merged into as few grounds as possible.
+/// A tile layer with the bool property `collision` set to false only has sprites, and hidden
+/// tile layers only have grounds. The `material` property sets the material of the grounds.
+///
+/// Objects become level entities by their type: `spawn`, `checkpoint`, `goal`, `ground`,
+/// `moving_platform`, `hazard`, `coin` or `gem`. Grounds take the same `material` property, and
+/// hazards take `kind` and `damage`. Moving platforms are grounds with the object property `path`,
+/// a polyline their center moves along and back, and the float property `speed` in pixels per
+/// second
+pub fn import(path: &Path) -> Result<LevelData, TiledError> {
+    let source = std::fs::read_to_string(resolve_path(path)).map_err(TiledError::Io)?;
+    let map: TiledMap = serde_json::from_str(&source).map_err(TiledError::Parse)?;
+
+    if map.orientation != "orthogonal" {
+        return Err(TiledError::Map(format!(
+            "{} maps are not supported, only orthogonal ones",
+            map.orientation
+        )));
+    }
+    if map.infinite {
+        return Err(TiledError::Map(
+            "infinite maps are not supported".to_string(),
+        ));
+    }
+
+    let name = match property(&map.properties, "name").and_then(Value::as_str) {
+        Some(name) => name.to_string(),
+        None => path
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().to_string()),
+    };
+
+    let mut import = Import::new(&map, name);
+    import.tilesets(path.parent().unwrap_or(Path::new("")))?;
+    import.paths(&map.layers);
+    import.layers(&map.layers)?;
+
+    if import.spawn.is_none() {
+        return Err(TiledError::Map(
+            "the map has no object of type `spawn`".to_string(),
+        ));
+    }
+    Ok(import.level)
+}
+
+#[derive(Deserialize)]
+struct TiledMap {
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    layers: Vec<TiledLayer>,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+struct TiledTileset {
+    firstgid: u32,
+    #[serde(default)]
+    name: String,
+    /// Only set for external tilesets
+    source: Option<String>,
+    /// Not set for tilesets made of separate images
+    image: Option<String>,
+    #[serde(default)]
+    tilewidth: f32,
+    #[serde(default)]
+    tileheight: f32,
+    #[serde(default)]
+    columns: usize,
+    #[serde(default)]
+    tilecount: usize,
+    #[serde(default)]
+    spacing: f32,
+    #[serde(default)]
+    margin: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum TiledLayer {
+    #[serde(rename = "tilelayer")]
+    Tiles(TileLayer),
+    #[serde(rename = "objectgroup")]
+    Objects(ObjectLayer),
+    #[serde(rename = "imagelayer")]
+    Image(LayerInfo),
+    #[serde(rename = "group")]
+    Group(GroupLayer),
+}
+
+/// Fields every kind of layer has
+#[derive(Deserialize)]
+struct LayerInfo {
+    name: String,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default = "LayerInfo::default_visible")]
+    visible: bool,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+impl LayerInfo {
+    fn default_visible() -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize)]
+struct TileLayer {
+    #[serde(flatten)]
+    info: LayerInfo,
+    width: usize,
+    height: usize,
+    /// An array of tile IDs, or a string when the layer is encoded
+    #[serde(default)]
+    data: Value,
+    encoding: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ObjectLayer {
+    #[serde(flatten)]
+    info: LayerInfo,
+    objects: Vec<TiledObject>,
+}
+
+#[derive(Deserialize)]
+struct GroupLayer {
+    #[serde(flatten)]
+    info: LayerInfo,
+    layers: Vec<TiledLayer>,
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type", alias = "class")]
+    kind: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    ellipse: bool,
+    polygon: Option<Value>,
+    polyline: Option<Vec<TiledPoint>>,
+    text: Option<Value>,
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+/// Point of a polyline, relative to its object
+#[derive(Deserialize)]
+struct TiledPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct TiledProperty {
+    name: String,
+    value: Value,
+}
+
+fn property<'a>(properties: &'a [TiledProperty], name: &str) -> Option<&'a Value> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .map(|property| &property.value)
+}
+
+/// Every object in `layers`, including the ones in groups
+fn collect_objects<'a>(layers: &'a [TiledLayer], objects: &mut Vec<&'a TiledObject>) {
+    for layer in layers {
+        match layer {
+            TiledLayer::Objects(layer) => objects.extend(layer.objects.iter()),
+            TiledLayer::Group(layer) => collect_objects(&layer.layers, objects),
+            TiledLayer::Tiles(_) | TiledLayer::Image(_) => {}
+        }
+    }
+}
+
+fn material(properties: &[TiledProperty]) -> Result<GroundMaterialKind, String> {
+    parse_material(
+        property(properties, "material"),
+        property(properties, "bounce"),
+    )
+}
+
+struct Import<'a> {
+    map: &'a TiledMap,
+    /// First tile ID of each of the level's tilesets
+    first_gids: Vec<u32>,
+    /// ID of the spawn object
+    spawn: Option<u32>,
+    /// Points of the polylines moving platforms follow, by object ID
+    paths: HashMap<u32, Vec<Vec2>>,
+    level: LevelData,
+}
+
+impl<'a> Import<'a> {
+    fn new(map: &'a TiledMap, name: String) -> Self {
+        Self {
+            map,
+            first_gids: Vec::new(),
+            spawn: None,
+            paths: HashMap::new(),
+            level: LevelData {
+                name,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Converts a position in the map, where y points down from the top, to the world
+    fn point(&self, x: f32, y: f32) -> Vec2 {
+        Vec2::new(x, self.map.height as f32 * self.map.tileheight - y)
+    }
+
+    fn tilesets(&mut self, dir: &Path) -> Result<(), TiledError> {
+        for tileset in self.map.tilesets.iter() {
+            if let Some(source) = &tileset.source {
+                return Err(TiledError::Map(format!(
+                    "external tileset `{source}` is not supported, embed it in the map"
+                )));
+            }
+            let Some(image) = &tileset.image else {
+                return Err(TiledError::Map(format!(
+                    "tileset `{}` is a collection of images, which is not supported",
+                    tileset.name
+                )));
+            };
+            if tileset.tilewidth != self.map.tilewidth || tileset.tileheight != self.map.tileheight
+            {
+                return Err(TiledError::Map(format!(
+                    "tileset `{}` has a different tile size than the map",
+                    tileset.name
+                )));
+            }
+            let image = asset_path(dir, image).ok_or_else(|| {
+                TiledError::Map(format!(
+                    "image `{image}` of tileset `{}` is outside the assets folder",
+                    tileset.name
+                ))
+            })?;
+
+            let columns = tileset.columns.max(1);
+            self.level.tilesets.push(TilesetData {
+                image,
+                tile_size: Vec2::new(tileset.tilewidth, tileset.tileheight),
+                columns,
+                rows: tileset.tilecount.div_ceil(columns),
+                spacing: tileset.spacing,
+                margin: tileset.margin,
+            });
+            self.first_gids.push(tileset.firstgid);
+        }
+        Ok(())
+    }
+
+    /// Finds the polylines named by `path` properties, so they aren't imported as entities
+    fn paths(&mut self, layers: &[TiledLayer]) {
+        let mut objects = Vec::new();
+        collect_objects(layers, &mut objects);
+
+        let referenced = objects
+            .iter()
+            .filter_map(|object| property(&object.properties, "path").and_then(Value::as_u64))
+            .collect::<HashSet<_>>();
+        for object in objects {
+            let Some(polyline) = &object.polyline else {
+                continue;
+            };
+            if !referenced.contains(&(object.id as u64)) {
+                continue;
+            }
+
+            let points = polyline
+                .iter()
+                .map(|point| self.point(object.x + point.x, object.y + point.y))
+                .collect();
+            self.paths.insert(object.id, points);
+        }
+    }
+
+    fn layers(&mut self, layers: &[TiledLayer]) -> Result<(), TiledError> {
+        for layer in layers {
+            let info = match layer {
+                TiledLayer::Tiles(layer) => &layer.info,
+                TiledLayer::Objects(layer) => &layer.info,
+                TiledLayer::Image(info) => info,
+                TiledLayer::Group(layer) => &layer.info,
+            };
+            if info.offsetx != 0f32 || info.offsety != 0f32 {
+                return Err(TiledError::Map(format!(
+                    "layer `{}` has an offset, which is not supported",
+                    info.name
+                )));
+            }
+
+            match layer {
+                TiledLayer::Tiles(layer) => self.tile_layer(layer)?,
+                TiledLayer::Objects(layer) => {
+                    for object in layer.objects.iter() {
+                        self.object(object)?;
+                    }
+                }
+                TiledLayer::Image(info) => {
+                    return Err(TiledError::Map(format!(
+                        "image layer `{}` is not supported",
+                        info.name
+                    )))
+                }
+                TiledLayer::Group(layer) => self.layers(&layer.layers)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn tile_layer(&mut self, layer: &TileLayer) -> Result<(), TiledError> {
+        let name = &layer.info.name;
+        if layer
+            .encoding
+            .as_deref()
+            .is_some_and(|encoding| encoding != "csv")
+        {
+            return Err(TiledError::Map(format!(
+                "tile layer `{name}` is encoded, set its tile layer format to CSV"
+            )));
+        }
+        let data: Vec<u32> =
+            serde_json::from_value(layer.data.clone()).map_err(TiledError::Parse)?;
+        if data.len() != layer.width * layer.height {
+            return Err(TiledError::Map(format!(
+                "tile layer `{name}` has {} tiles instead of {}",
+                data.len(),
+                layer.width * layer.height
+            )));
+        }
+
+        let collision = property(&layer.info.properties, "collision")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        let material = material(&layer.info.properties)
+            .map_err(|message| TiledError::Map(format!("tile layer `{name}`: {message}")))?;
+
+        let mut solid = vec![false; data.len()];
+        for (i, gid) in data.into_iter().enumerate() {
+            if gid == 0 {
+                continue;
+            }
+            solid[i] = collision;
+            if layer.info.visible {
+                self.tile(name, gid, i % layer.width, i / layer.width)?;
+            }
+        }
+
+        let tile_size = Vec2::new(self.map.tilewidth, self.map.tileheight);
+        for (i, (col, row, w, h)) in merge_cells(&solid, layer.width).into_iter().enumerate() {
+            let size = Vec2::new(w as f32, h as f32) * tile_size;
+            let corner = Vec2::new(col as f32, row as f32) * tile_size;
+            self.level.grounds.push(GroundData {
+                name: format!("{name} {}", i + 1),
+                position: self.point(corner.x + size.x / 2f32, corner.y + size.y / 2f32),
+                size,
+                material,
+                // The tiles are drawn instead
+                color: Some(Color::NONE),
+                one_way: false,
+                path: None,
+            });
+        }
+        Ok(())
+    }
+
+    fn tile(&mut self, layer: &str, gid: u32, col: usize, row: usize) -> Result<(), TiledError> {
+        if gid & (FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL) != 0 {
+            return Err(TiledError::Map(format!(
+                "tile layer `{layer}` has a rotated tile at column {col}, row {row}"
+            )));
+        }
+
+        let id = gid & GID_MASK;
+        let Some(tileset) = self.first_gids.iter().rposition(|first| *first <= id) else {
+            return Err(TiledError::Map(format!(
+                "tile layer `{layer}` has tile {id}, which is in no tileset, at column {col}, row {row}"
+            )));
+        };
+
+        // Tilesets are imported in map order
+        let index = (id - self.first_gids[tileset]) as usize;
+        let tiled_tileset = &self.map.tilesets[tileset];
+        if index >= tiled_tileset.tilecount {
+            return Err(TiledError::Map(format!(
+                "tile layer `{layer}` has tile {id}, which is past the end of tileset `{}`, at column {col}, row {row}",
+                tiled_tileset.name
+            )));
+        }
+
+        let position = self.point(
+            (col as f32 + 0.5f32) * self.map.tilewidth,
+            (row as f32 + 0.5f32) * self.map.tileheight,
+        );
+        self.level.tiles.push(TileData {
+            tileset,
+            index,
+            position,
+            flip_x: gid & FLIPPED_HORIZONTALLY != 0,
+            flip_y: gid & FLIPPED_VERTICALLY != 0,
+        });
+        Ok(())
+    }
+
+    fn object(&mut self, object: &TiledObject) -> Result<(), TiledError> {
+        let error = |message: &str| TiledError::Object {
+            id: object.id,
+            message: message.to_string(),
+        };
+
+        if object.gid.is_some() {
+            return Err(error("tile objects are not supported"));
+        }
+        if self.paths.contains_key(&object.id) {
+            // Only there for a moving platform to follow
+            return Ok(());
+        }
+        if object.ellipse
+            || object.polygon.is_some()
+            || object.polyline.is_some()
+            || object.text.is_some()
+        {
+            return Err(error("only rectangle and point objects are supported"));
+        }
+        if object.rotation != 0f32 {
+            return Err(error("rotated objects are not supported"));
+        }
+
+        let kind = object.kind.to_lowercase();
+        let size = Vec2::new(object.width, object.height);
+        let position = self.point(object.x + size.x / 2f32, object.y + size.y / 2f32);
+        let name = if object.name.is_empty() {
+            format!("{} {}", object.kind, object.id)
+        } else {
+            object.name.clone()
+        };
+        let has_area = size.x > 0f32 && size.y > 0f32;
+
+        match kind.as_str() {
+            "spawn" => {
+                if let Some(spawn) = self.spawn {
+                    return Err(error(&format!("object {spawn} is already the spawn point")));
+                }
+                self.spawn = Some(object.id);
+                self.level.spawn = position;
+            }
+            "checkpoint" => self.level.checkpoints.push(position),
+            "goal" => {
+                if self.level.goal.is_some() {
+                    return Err(error("the map already has a goal"));
+                }
+                self.level.goal = Some(position);
+            }
+            "ground" => {
+                if !has_area {
+                    return Err(error("grounds need a width and height"));
+                }
+                self.level.grounds.push(GroundData {
+                    name,
+                    position,
+                    size,
+                    material: material(&object.properties).map_err(|message| error(&message))?,
+                    color: None,
+                    one_way: false,
+                    path: None,
+                });
+            }
+            "hazard" => {
+                if !has_area {
+                    return Err(error("hazards need a width and height"));
+                }
+                let (kind, damage) = parse_hazard(
+                    property(&object.properties, "kind"),
+                    property(&object.properties, "damage"),
+                )
+                .map_err(|message| error(&message))?;
+                self.level.hazards.push(HazardData {
+                    name,
+                    position,
+                    size,
+                    kind,
+                    damage,
+                });
+            }
+            "coin" | "gem" => self.level.collectibles.push(CollectibleData {
+                name,
+                position,
+                kind: if kind == "gem" {
+                    CollectibleKind::Gem
+                } else {
+                    CollectibleKind::Coin
+                },
+            }),
+            "moving_platform" | "movingplatform" => {
+                if !has_area {
+                    return Err(error("moving platforms need a width and height"));
+                }
+                let points = property(&object.properties, "path")
+                    .and_then(Value::as_u64)
+                    .and_then(|id| self.paths.get(&(id as u32)))
+                    .ok_or_else(|| {
+                        error("moving platforms need an object property `path` set to a polyline")
+                    })?
+                    .iter()
+                    .map(|point| *point - position)
+                    .collect();
+                let speed = property(&object.properties, "speed")
+                    .and_then(Value::as_f64)
+                    .filter(|speed| *speed > 0f64)
+                    .ok_or_else(|| {
+                        error("moving platforms need a positive float property `speed`")
+                    })?;
+                self.level.grounds.push(GroundData {
+                    name,
+                    position,
+                    size,
+                    material: material(&object.properties).map_err(|message| error(&message))?,
+                    color: None,
+                    one_way: false,
+                    path: Some(GroundPath {
+                        points,
+                        speed: speed as f32,
+                    }),
+                });
+            }
+            "" => {
+                return Err(error(&format!(
+                    "the object has no type, expected {OBJECT_TYPES}"
+                )));
+            }
+            _ => {
+                return Err(error(&format!(
+                    "unknown object type `{}`, expected {OBJECT_TYPES}",
+                    object.kind
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::HazardKind;
+    use serde_json::json;
+
+    /// A 10 by 10 map of 25 pixel tiles with `objects` in one layer
+    fn map(objects: Value) -> TiledMap {
+        serde_json::from_value(json!({
+            "orientation": "orthogonal",
+            "height": 10,
+            "tilewidth": 25,
+            "tileheight": 25,
+            "layers": [{ "type": "objectgroup", "name": "Entities", "objects": objects }],
+        }))
+        .unwrap()
+    }
+
+    fn import_objects(map: &TiledMap) -> Result<LevelData, TiledError> {
+        let mut import = Import::new(map, String::new());
+        import.paths(&map.layers);
+        import.layers(&map.layers)?;
+        Ok(import.level)
+    }
+
+    #[test]
+    fn imports_example_map() {
+        let level = import(Path::new("assets/levels/tiled/example.tmj")).unwrap();
+
+        assert_eq!(level.name, "Tiled example");
+        assert_eq!(level.spawn, Vec2::new(50f32, 100f32));
+        assert_eq!(level.tilesets.len(), 1);
+        assert_eq!(level.tilesets[0].image, "tilesets/basic.png");
+        assert_eq!((level.tilesets[0].columns, level.tilesets[0].rows), (2, 1));
+
+        // Ground, ice and decoration tiles. The hidden walls have no sprites
+        assert_eq!(level.tiles.len(), 72 + 6 + 7);
+        // The decoration doesn't collide
+        assert!(level
+            .grounds
+            .iter()
+            .all(|ground| !ground.name.starts_with("Decoration")));
+
+        let ground = |name: &str| {
+            level
+                .grounds
+                .iter()
+                .find(|ground| ground.name == name)
+                .unwrap_or_else(|| panic!("no ground {name}"))
+        };
+        assert_eq!(level.grounds.len(), 5);
+        // Both rows of the floor on each side of the gap are merged
+        assert_eq!(ground("Ground 1").size, Vec2::new(400f32, 50f32));
+        assert_eq!(ground("Ground 1").position, Vec2::new(200f32, 25f32));
+        assert_eq!(ground("Ground 2").size, Vec2::new(500f32, 50f32));
+        assert_eq!(ground("Ground 2").position, Vec2::new(750f32, 25f32));
+        assert_eq!(ground("Ice 1").material, GroundMaterialKind::Ice);
+        assert_eq!(ground("Ice 1").size, Vec2::new(150f32, 25f32));
+        assert_eq!(ground("Walls 1").size, Vec2::new(25f32, 250f32));
+        assert_eq!(ground("Walls 2").position, Vec2::new(987.5f32, 175f32));
+
+        assert_eq!(level.hazards.len(), 2);
+        assert_eq!(level.hazards[0].kind, HazardKind::Lava);
+        assert_eq!(level.collectibles.len(), 3);
+        assert_eq!(level.checkpoints, vec![Vec2::new(530f32, 80f32)]);
+        assert_eq!(level.goal, Some(Vec2::new(965f32, 80f32)));
+    }
+
+    #[test]
+    fn tiles_past_the_tileset_are_rejected() {
+        let err = import(Path::new("tests/fixtures/tiled/tile_past_the_tileset.tmj"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`Ground`"), "{err}");
+        assert!(err.contains("column 1, row 0"), "{err}");
+    }
+
+    #[test]
+    fn point_flips_y() {
+        let map = map(json!([]));
+        let import = Import::new(&map, String::new());
+
+        assert_eq!(import.point(30f32, 0f32), Vec2::new(30f32, 250f32));
+        assert_eq!(import.point(30f32, 250f32), Vec2::new(30f32, 0f32));
+        assert_eq!(import.point(0f32, 100f32), Vec2::new(0f32, 150f32));
+    }
+
+    #[test]
+    fn unknown_object_type_reports_object() {
+        let map = map(json!([
+            { "id": 1, "type": "spawn", "x": 0, "y": 0 },
+            { "id": 7, "type": "teleporter", "x": 50, "y": 50 },
+        ]));
+
+        assert!(matches!(
+            import_objects(&map),
+            Err(TiledError::Object { id: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn moving_platform_follows_polyline() {
+        let map = map(json!([
+            {
+                "id": 1,
+                "type": "moving_platform",
+                "x": 100,
+                "y": 100,
+                "width": 50,
+                "height": 25,
+                "properties": [
+                    { "name": "path", "type": "object", "value": 2 },
+                    { "name": "speed", "type": "float", "value": 60 },
+                ],
+            },
+            {
+                "id": 2,
+                "x": 125,
+                "y": 112.5,
+                "polyline": [{ "x": 0, "y": 0 }, { "x": 100, "y": -50 }],
+            },
+        ]));
+
+        let level = import_objects(&map).unwrap();
+        assert_eq!(level.grounds.len(), 1);
+        assert_eq!(level.grounds[0].position, Vec2::new(125f32, 137.5f32));
+        assert_eq!(
+            level.grounds[0].path,
+            Some(GroundPath {
+                points: vec![Vec2::ZERO, Vec2::new(100f32, 50f32)],
+                speed: 60f32,
+            })
+        );
+    }
+
+    #[test]
+    fn moving_platform_needs_path() {
+        let map = map(json!([{
+            "id": 3,
+            "type": "moving_platform",
+            "x": 0,
+            "y": 0,
+            "width": 50,
+            "height": 25,
+            "properties": [{ "name": "speed", "type": "float", "value": 60 }],
+        }]));
+
+        assert!(matches!(
+            import_objects(&map),
+            Err(TiledError::Object { id: 3, .. })
+        ));
+    }
+}
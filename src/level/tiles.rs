@@ -0,0 +1,105 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::LevelEntity;
+
+pub(super) struct TilesPlugin;
+
+impl Plugin for TilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, load_tile_sprites);
+    }
+}
+
+/// A tileset image cut into a grid of tiles
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TilesetData {
+    /// Path of the image, relative to the assets folder
+    pub image: String,
+    pub tile_size: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+    /// Space between tiles
+    #[serde(default)]
+    pub spacing: f32,
+    /// Space around the edge of the image
+    #[serde(default)]
+    pub margin: f32,
+}
+
+/// A decorative tile, drawn without a collider
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TileData {
+    /// Index into the level's tilesets
+    pub tileset: usize,
+    /// Index of the tile in the tileset, row by row
+    pub index: usize,
+    pub position: Vec2,
+    #[serde(default)]
+    pub flip_x: bool,
+    #[serde(default)]
+    pub flip_y: bool,
+}
+
+/// Tile that gets its sprite once the tileset is loaded by [`load_tile_sprites`]
+#[derive(Component, Clone, Debug)]
+pub struct TileSprite {
+    pub tileset: TilesetData,
+    pub index: usize,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+pub fn spawn_tile(cmd: &mut Commands, tilesets: &[TilesetData], tile: &TileData) -> Option<Entity> {
+    let tileset = tilesets.get(tile.tileset)?;
+
+    Some(
+        cmd.spawn((
+            SpatialBundle::from_transform(Transform::from_translation(tile.position.extend(-2f32))),
+            TileSprite {
+                tileset: tileset.clone(),
+                index: tile.index,
+                flip_x: tile.flip_x,
+                flip_y: tile.flip_y,
+            },
+            LevelEntity,
+        ))
+        .id(),
+    )
+}
+
+fn load_tile_sprites(
+    mut cmd: Commands,
+    tile_query: Query<(Entity, &TileSprite), Added<TileSprite>>,
+    asset_server: Res<AssetServer>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    mut loaded: Local<HashMap<String, Handle<TextureAtlas>>>,
+) {
+    for (entity, tile) in tile_query.iter() {
+        let tileset = &tile.tileset;
+        let atlas = loaded
+            .entry(tileset.image.clone())
+            .or_insert_with(|| {
+                atlases.add(TextureAtlas::from_grid(
+                    asset_server.load(&tileset.image),
+                    tileset.tile_size,
+                    tileset.columns,
+                    tileset.rows,
+                    Some(Vec2::splat(tileset.spacing)),
+                    Some(Vec2::splat(tileset.margin)),
+                ))
+            })
+            .clone();
+
+        cmd.entity(entity).insert((
+            TextureAtlasSprite {
+                index: tile.index,
+                flip_x: tile.flip_x,
+                flip_y: tile.flip_y,
+                custom_size: Some(tileset.tile_size),
+                ..Default::default()
+            },
+            atlas,
+        ));
+    }
+}
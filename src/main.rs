@@ -1,24 +1,36 @@
-use bevy::{app::PluginGroupBuilder, prelude::*, window::WindowMode};
+use bevy::{
+    app::PluginGroupBuilder, asset::ChangeWatcher, prelude::*, utils::Duration, window::WindowMode,
+};
 use bevy_rapier2d::prelude::*;
 use seldom_state::StateMachinePlugin;
 
+#[cfg(feature = "debug")]
+pub mod debug;
 pub mod exit;
+pub mod key_sequence;
 pub mod level;
+pub mod menu;
+pub mod particles;
 pub mod player;
 
-pub const DEBUG: bool = true;
-
-pub const fn debug() -> bool {
-    DEBUG
-}
+/// Downwards acceleration of the physics world, in pixels per second squared. Characters scale it
+/// with their preset's `gravity_scale`
+pub const GRAVITY: f32 = 1100f32;
 
 struct GamePlugins;
 
 impl PluginGroup for GamePlugins {
     fn build(self) -> PluginGroupBuilder {
-        PluginGroupBuilder::start::<Self>()
+        let group = PluginGroupBuilder::start::<Self>()
+            .add(menu::MenuPlugin)
             .add(level::LevelPlugin)
             .add(player::PlayerPlugin)
+            .add(particles::ParticlePlugin);
+
+        #[cfg(feature = "debug")]
+        let group = group.add(debug::DebugPlugin);
+
+        group
     }
 }
 
@@ -28,12 +40,41 @@ impl PluginGroup for OtherPlugins {
     fn build(self) -> PluginGroupBuilder {
         PluginGroupBuilder::start::<Self>()
             .add(StateMachinePlugin::default())
-            .add(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100f32))
-            .add(exit::ExitPlugin::default())
+            .add(RapierPhysicsPlugin::<level::LevelPhysicsHooks>::pixels_per_meter(100f32))
+            .add(exit::ExitPlugin {
+                mode: exit::ExitMode::Confirm,
+                ..Default::default()
+            })
     }
 }
 
 fn main() {
+    let args = std::env::args().collect::<Vec<String>>();
+    if let Some(i) = args.iter().position(|arg| arg == "--export-state-graph") {
+        let path = args
+            .get(i + 1)
+            .map_or("player_state_machine.dot", |p| p.as_str());
+        if let Err(err) = player::state_machine::export_state_graph(path.as_ref()) {
+            println!("Could not write player state graph to {path}: {err}");
+            std::process::exit(1);
+        }
+        println!("Wrote player state graph to {path}");
+        return;
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--validate-level") {
+        let passed = level::reachability::validate_levels(&args[i + 1..]);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--generate-level") {
+        let Some(seed) = args.get(i + 1) else {
+            println!("Usage: --generate-level <seed> [path]");
+            std::process::exit(1);
+        };
+        let passed =
+            level::generator::generate_level_file(seed, args.get(i + 2).map(|p| p.as_str()));
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     App::new()
         .add_plugins((
             DefaultPlugins
@@ -45,12 +86,17 @@ fn main() {
                     }),
                     ..Default::default()
                 })
-                .set(ImagePlugin::default_nearest()),
+                .set(ImagePlugin::default_nearest())
+                // Hot reloads character presets
+                .set(AssetPlugin {
+                    watch_for_changes: ChangeWatcher::with_delay(Duration::from_millis(200)),
+                    ..Default::default()
+                }),
             GamePlugins,
             OtherPlugins,
         ))
         .insert_resource(RapierConfiguration {
-            gravity: Vec2::new(0f32, -1100f32),
+            gravity: Vec2::new(0f32, -GRAVITY),
             ..Default::default()
         })
         .run();
@@ -0,0 +1,550 @@
+use bevy::{
+    prelude::*,
+    reflect::TypePath,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::{axislike::SingleAxis, prelude::*};
+
+use crate::{exit::RequestExit, level::LevelState};
+
+pub(super) struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .add_plugins(InputManagerPlugin::<MenuAction>::default())
+            .init_resource::<ActionState<MenuAction>>()
+            .insert_resource(menu_input_map())
+            .init_resource::<MenuSelection>()
+            .init_resource::<SettingsReturn>()
+            .init_resource::<QuitReturn>()
+            .init_resource::<GameSettings>()
+            .init_resource::<PhysicsPause>()
+            .add_systems(
+                OnEnter(AppState::MainMenu),
+                (spawn_main_menu, pause_physics),
+            )
+            .add_systems(OnEnter(AppState::Paused), spawn_pause_menu)
+            .add_systems(OnEnter(AppState::Settings), spawn_settings_menu)
+            .add_systems(OnEnter(AppState::ConfirmQuit), spawn_confirm_quit_menu)
+            .add_systems(OnEnter(AppState::InGame), resume_physics)
+            .add_systems(OnExit(AppState::InGame), pause_physics)
+            .add_systems(OnExit(AppState::MainMenu), despawn_menu)
+            .add_systems(OnExit(AppState::Paused), despawn_menu)
+            .add_systems(OnExit(AppState::Settings), despawn_menu)
+            .add_systems(OnExit(AppState::ConfirmQuit), despawn_menu)
+            .add_systems(Update, pause_game.run_if(in_state(AppState::InGame)))
+            .add_systems(
+                Update,
+                (navigate_menu, activate_menu_button, highlight_selection)
+                    .chain()
+                    .run_if(not(in_state(AppState::InGame))),
+            )
+            .add_systems(
+                Update,
+                (apply_settings, refresh_button_labels).run_if(resource_changed::<GameSettings>()),
+            )
+            .add_systems(
+                PostUpdate,
+                apply_physics_pause
+                    .run_if(resource_changed::<PhysicsPause>())
+                    .before(PhysicsSet::SyncBackend),
+            );
+    }
+}
+
+/// Top level state of the application. Gameplay systems only run while `InGame`
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    InGame,
+    Paused,
+    Settings,
+    /// Asks whether to quit, opened by the [`ExitPlugin`](crate::exit::ExitPlugin) chord in
+    /// [`ExitMode::Confirm`](crate::exit::ExitMode::Confirm)
+    ConfirmQuit,
+}
+
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, TypePath)]
+pub enum MenuAction {
+    Up,
+    Down,
+    Select,
+    Back,
+    Pause,
+}
+
+fn menu_input_map() -> InputMap<MenuAction> {
+    InputMap::default()
+        .insert(KeyCode::Up, MenuAction::Up)
+        .insert(KeyCode::W, MenuAction::Up)
+        .insert(GamepadButtonType::DPadUp, MenuAction::Up)
+        .insert(
+            SingleAxis::positive_only(GamepadAxisType::LeftStickY, 0.5f32),
+            MenuAction::Up,
+        )
+        .insert(KeyCode::Down, MenuAction::Down)
+        .insert(KeyCode::S, MenuAction::Down)
+        .insert(GamepadButtonType::DPadDown, MenuAction::Down)
+        .insert(
+            SingleAxis::negative_only(GamepadAxisType::LeftStickY, -0.5f32),
+            MenuAction::Down,
+        )
+        .insert(KeyCode::Return, MenuAction::Select)
+        .insert(KeyCode::Space, MenuAction::Select)
+        .insert(GamepadButtonType::South, MenuAction::Select)
+        .insert(KeyCode::Escape, MenuAction::Back)
+        .insert(KeyCode::Back, MenuAction::Back)
+        .insert(GamepadButtonType::East, MenuAction::Back)
+        .insert(KeyCode::Escape, MenuAction::Pause)
+        .insert(GamepadButtonType::Start, MenuAction::Pause)
+        .build()
+}
+
+/// Settings changed from the settings screen, applied to the primary window
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct GameSettings {
+    pub fullscreen: bool,
+    pub vsync: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            fullscreen: true,
+            vsync: true,
+        }
+    }
+}
+
+/// Everything that currently keeps physics paused, so resuming from one doesn't undo the other
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhysicsPause {
+    /// Outside of the game, in any menu
+    pub menu: bool,
+    /// While the level editor is open
+    pub editor: bool,
+}
+
+impl PhysicsPause {
+    pub fn paused(&self) -> bool {
+        self.menu || self.editor
+    }
+}
+
+/// State the settings screen goes back to
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct SettingsReturn(pub AppState);
+
+/// State the quit confirmation goes back to when cancelled
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct QuitReturn(pub AppState);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuCommand {
+    Play,
+    Resume,
+    Settings,
+    ToggleFullscreen,
+    ToggleVsync,
+    Back,
+    MainMenu,
+    Quit,
+    CancelQuit,
+}
+
+impl MenuCommand {
+    pub fn label(&self, settings: &GameSettings) -> String {
+        let on_off = |on| if on { "On" } else { "Off" };
+
+        match self {
+            MenuCommand::Play => "Play".to_string(),
+            MenuCommand::Resume => "Resume".to_string(),
+            MenuCommand::Settings => "Settings".to_string(),
+            MenuCommand::ToggleFullscreen => {
+                format!("Fullscreen: {}", on_off(settings.fullscreen))
+            }
+            MenuCommand::ToggleVsync => format!("VSync: {}", on_off(settings.vsync)),
+            MenuCommand::Back => "Back".to_string(),
+            MenuCommand::MainMenu => "Main menu".to_string(),
+            MenuCommand::Quit => "Quit".to_string(),
+            MenuCommand::CancelQuit => "Cancel".to_string(),
+        }
+    }
+}
+
+/// Root node of the current menu screen, despawned when the screen is left
+#[derive(Component)]
+pub struct MenuRoot;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MenuButton {
+    pub index: usize,
+    pub command: MenuCommand,
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct MenuSelection {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl MenuButton {
+    const COLOR: Color = Color::rgb(0.15f32, 0.15f32, 0.2f32);
+    const SELECTED_COLOR: Color = Color::rgb(0.3f32, 0.45f32, 0.7f32);
+}
+
+fn spawn_main_menu(
+    mut cmd: Commands,
+    mut selection: ResMut<MenuSelection>,
+    settings: Res<GameSettings>,
+) {
+    spawn_menu(
+        &mut cmd,
+        &mut selection,
+        &settings,
+        "Bevy Platformer",
+        &[MenuCommand::Play, MenuCommand::Settings, MenuCommand::Quit],
+    );
+}
+
+fn spawn_pause_menu(
+    mut cmd: Commands,
+    mut selection: ResMut<MenuSelection>,
+    settings: Res<GameSettings>,
+) {
+    spawn_menu(
+        &mut cmd,
+        &mut selection,
+        &settings,
+        "Paused",
+        &[
+            MenuCommand::Resume,
+            MenuCommand::Settings,
+            MenuCommand::MainMenu,
+            MenuCommand::Quit,
+        ],
+    );
+}
+
+fn spawn_settings_menu(
+    mut cmd: Commands,
+    mut selection: ResMut<MenuSelection>,
+    settings: Res<GameSettings>,
+) {
+    spawn_menu(
+        &mut cmd,
+        &mut selection,
+        &settings,
+        "Settings",
+        &[
+            MenuCommand::ToggleFullscreen,
+            MenuCommand::ToggleVsync,
+            MenuCommand::Back,
+        ],
+    );
+}
+
+fn spawn_confirm_quit_menu(
+    mut cmd: Commands,
+    mut selection: ResMut<MenuSelection>,
+    settings: Res<GameSettings>,
+) {
+    spawn_menu(
+        &mut cmd,
+        &mut selection,
+        &settings,
+        "Quit the game?",
+        &[MenuCommand::CancelQuit, MenuCommand::Quit],
+    );
+}
+
+/// Spawns a centered column of buttons over a dimmed background, with the first button selected
+pub fn spawn_menu(
+    cmd: &mut Commands,
+    selection: &mut MenuSelection,
+    settings: &GameSettings,
+    title: &str,
+    commands: &[MenuCommand],
+) {
+    *selection = MenuSelection {
+        index: 0,
+        count: commands.len(),
+    };
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100f32),
+                height: Val::Percent(100f32),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12f32),
+                ..Default::default()
+            },
+            background_color: Color::rgba(0f32, 0f32, 0f32, 0.6f32).into(),
+            ..Default::default()
+        },
+        MenuRoot,
+        Name::from(format!("{title} menu")),
+    ))
+    .with_children(|root| {
+        root.spawn(
+            TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 64f32,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            )
+            .with_style(Style {
+                margin: UiRect::bottom(Val::Px(24f32)),
+                ..Default::default()
+            }),
+        );
+
+        for (index, command) in commands.iter().enumerate() {
+            root.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(280f32),
+                        height: Val::Px(56f32),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    background_color: MenuButton::COLOR.into(),
+                    ..Default::default()
+                },
+                MenuButton {
+                    index,
+                    command: *command,
+                },
+            ))
+            .with_children(|button| {
+                button.spawn(TextBundle::from_section(
+                    command.label(settings),
+                    TextStyle {
+                        font_size: 32f32,
+                        color: Color::WHITE,
+                        ..Default::default()
+                    },
+                ));
+            });
+        }
+    });
+}
+
+fn despawn_menu(mut cmd: Commands, menu_query: Query<Entity, With<MenuRoot>>) {
+    for entity in menu_query.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+fn pause_physics(mut pause: ResMut<PhysicsPause>) {
+    pause.menu = true;
+}
+
+fn resume_physics(mut pause: ResMut<PhysicsPause>) {
+    pause.menu = false;
+}
+
+fn apply_physics_pause(pause: Res<PhysicsPause>, mut rapier: ResMut<RapierConfiguration>) {
+    rapier.physics_pipeline_active = !pause.paused();
+}
+
+fn pause_game(input: Res<ActionState<MenuAction>>, mut next_state: ResMut<NextState<AppState>>) {
+    if input.just_pressed(MenuAction::Pause) {
+        next_state.set(AppState::Paused);
+    }
+}
+
+fn navigate_menu(
+    input: Res<ActionState<MenuAction>>,
+    mut selection: ResMut<MenuSelection>,
+    button_query: Query<(&MenuButton, &Interaction), Changed<Interaction>>,
+) {
+    if selection.count == 0 {
+        return;
+    }
+
+    if input.just_pressed(MenuAction::Up) {
+        selection.index = (selection.index + selection.count - 1) % selection.count;
+    }
+    if input.just_pressed(MenuAction::Down) {
+        selection.index = (selection.index + 1) % selection.count;
+    }
+
+    // The mouse selects whatever it hovers
+    for (button, interaction) in button_query.iter() {
+        if *interaction != Interaction::None {
+            selection.index = button.index;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn activate_menu_button(
+    input: Res<ActionState<MenuAction>>,
+    button_query: Query<&MenuButton>,
+    clicked_query: Query<(&MenuButton, &Interaction), Changed<Interaction>>,
+    selection: Res<MenuSelection>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut next_level_state: ResMut<NextState<LevelState>>,
+    mut settings_return: ResMut<SettingsReturn>,
+    quit_return: Res<QuitReturn>,
+    mut settings: ResMut<GameSettings>,
+    mut exit: EventWriter<RequestExit>,
+) {
+    let clicked = clicked_query
+        .iter()
+        .find(|(_, interaction)| **interaction == Interaction::Pressed)
+        .map(|(button, _)| button.command);
+    let selected = input
+        .just_pressed(MenuAction::Select)
+        .then(|| {
+            button_query
+                .iter()
+                .find(|button| button.index == selection.index)
+                .map(|button| button.command)
+        })
+        .flatten();
+
+    let back = match state.get() {
+        AppState::Paused => Some(MenuCommand::Resume),
+        AppState::Settings => Some(MenuCommand::Back),
+        AppState::ConfirmQuit => Some(MenuCommand::CancelQuit),
+        _ => None,
+    };
+    let command = clicked
+        .or(selected)
+        .or(back.filter(|_| input.just_pressed(MenuAction::Back)));
+
+    let Some(command) = command else {
+        return;
+    };
+
+    match command {
+        MenuCommand::Play => {
+            next_state.set(AppState::InGame);
+            next_level_state.set(LevelState::Loading);
+        }
+        MenuCommand::Resume => next_state.set(AppState::InGame),
+        MenuCommand::Settings => {
+            settings_return.0 = *state.get();
+            next_state.set(AppState::Settings);
+        }
+        MenuCommand::ToggleFullscreen => settings.fullscreen = !settings.fullscreen,
+        MenuCommand::ToggleVsync => settings.vsync = !settings.vsync,
+        MenuCommand::Back => next_state.set(settings_return.0),
+        MenuCommand::MainMenu => next_state.set(AppState::MainMenu),
+        // Picking quit in a menu is already a confirmation
+        MenuCommand::Quit => exit.send(RequestExit { confirmed: true }),
+        MenuCommand::CancelQuit => next_state.set(quit_return.0),
+    }
+}
+
+fn highlight_selection(
+    selection: Res<MenuSelection>,
+    mut button_query: Query<(&MenuButton, &mut BackgroundColor)>,
+) {
+    for (button, mut color) in button_query.iter_mut() {
+        *color = if button.index == selection.index {
+            MenuButton::SELECTED_COLOR
+        } else {
+            MenuButton::COLOR
+        }
+        .into();
+    }
+}
+
+fn apply_settings(
+    settings: Res<GameSettings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = window_query.get_single_mut() else {
+        return;
+    };
+
+    window.mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    window.present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+}
+
+fn refresh_button_labels(
+    settings: Res<GameSettings>,
+    button_query: Query<(&MenuButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (button, children) in button_query.iter() {
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = button.command.label(&settings);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the menu's physics pause alone, starting in the main menu
+    fn pause_app() -> App {
+        let mut app = App::new();
+        app.add_state::<AppState>()
+            .init_resource::<PhysicsPause>()
+            .insert_resource(RapierConfiguration::default())
+            .add_systems(OnEnter(AppState::MainMenu), pause_physics)
+            .add_systems(OnEnter(AppState::InGame), resume_physics)
+            .add_systems(OnExit(AppState::InGame), pause_physics)
+            .add_systems(
+                Update,
+                apply_physics_pause.run_if(resource_changed::<PhysicsPause>()),
+            );
+        app.update();
+        app
+    }
+
+    fn set_state(app: &mut App, state: AppState) {
+        app.world.resource_mut::<NextState<AppState>>().set(state);
+        app.update();
+    }
+
+    fn physics_active(app: &App) -> bool {
+        app.world
+            .resource::<RapierConfiguration>()
+            .physics_pipeline_active
+    }
+
+    #[test]
+    fn resuming_from_the_menu_keeps_the_editor_paused() {
+        let mut app = pause_app();
+        assert!(!physics_active(&app));
+
+        set_state(&mut app, AppState::InGame);
+        assert!(physics_active(&app));
+
+        app.world.resource_mut::<PhysicsPause>().editor = true;
+        set_state(&mut app, AppState::Paused);
+        assert!(!physics_active(&app));
+
+        set_state(&mut app, AppState::InGame);
+        assert!(!physics_active(&app));
+
+        app.world.resource_mut::<PhysicsPause>().editor = false;
+        app.update();
+        assert!(physics_active(&app));
+    }
+}
@@ -0,0 +1,312 @@
+use bevy::{prelude::*, render::texture::DEFAULT_IMAGE_HANDLE};
+use std::f32::consts::TAU;
+
+use crate::menu::AppState;
+
+pub(super) struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ParticleSettings::default())
+            .insert_resource(ParticleRng::default())
+            .add_event::<SpawnParticles>()
+            .add_systems(
+                Update,
+                (update_particles, spawn_particles)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .register_type::<EmitterDef>();
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct ParticleSettings {
+    pub max_particles: usize,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self { max_particles: 512 }
+    }
+}
+
+/// Data describing how the particles of an emitter look and move. Continuous emitters use `rate`,
+/// one-shot effects use `burst`.
+#[derive(Clone, Debug, Reflect)]
+pub struct EmitterDef {
+    pub rate: f32,
+    pub burst: usize,
+
+    pub lifetime: f32,
+    pub lifetime_variance: f32,
+    pub size: f32,
+    pub gradient: ColorGradient,
+
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Total angle in radians the particles are spread over, centered on the emit direction
+    pub spread: f32,
+    /// Distributes a burst evenly over the spread instead of randomly, e.g. for rings
+    pub even_spread: bool,
+    pub gravity: f32,
+    pub drag: f32,
+}
+
+impl Default for EmitterDef {
+    fn default() -> Self {
+        Self {
+            rate: 0f32,
+            burst: 0,
+
+            lifetime: 0.5f32,
+            lifetime_variance: 0f32,
+            size: 4f32,
+            gradient: ColorGradient::new(vec![(0f32, Color::WHITE), (1f32, Color::NONE)]),
+
+            min_speed: 0f32,
+            max_speed: 0f32,
+            spread: 0f32,
+            even_spread: false,
+            gravity: 0f32,
+            drag: 0f32,
+        }
+    }
+}
+
+/// Colors keyed on the normalized age of a particle, sampled linearly
+#[derive(Clone, Debug, Reflect)]
+pub struct ColorGradient(Vec<(f32, Color)>);
+
+impl ColorGradient {
+    pub fn new(mut keys: Vec<(f32, Color)>) -> Self {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self(keys)
+    }
+
+    pub fn sample(&self, t: f32) -> Color {
+        let (Some(first), Some(last)) = (self.0.first(), self.0.last()) else {
+            return Color::WHITE;
+        };
+
+        if t <= first.0 {
+            return first.1;
+        }
+
+        for window in self.0.windows(2) {
+            let ((from_t, from), (to_t, to)) = (window[0], window[1]);
+            if t <= to_t {
+                let t = (t - from_t) / (to_t - from_t).max(f32::EPSILON);
+                return Color::from(Vec4::from(from).lerp(Vec4::from(to), t));
+            }
+        }
+
+        last.1
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct SpawnParticles {
+    pub emitter: EmitterDef,
+    pub position: Vec2,
+    pub direction: Vec2,
+    pub count: usize,
+}
+
+impl SpawnParticles {
+    pub fn burst(emitter: &EmitterDef, position: Vec2, direction: Vec2) -> Self {
+        Self {
+            emitter: emitter.clone(),
+            position,
+            direction,
+            count: emitter.burst,
+        }
+    }
+}
+
+/// Small xorshift generator, particles don't need anything better
+#[derive(Resource)]
+pub struct ParticleRng(u32);
+
+impl Default for ParticleRng {
+    fn default() -> Self {
+        Self(0x9E37_79B9)
+    }
+}
+
+impl ParticleRng {
+    pub fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+#[derive(Component)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub age: f32,
+    pub lifetime: f32,
+    pub gravity: f32,
+    pub drag: f32,
+    pub gradient: ColorGradient,
+}
+
+fn spawn_particles(
+    mut cmd: Commands,
+    mut events: EventReader<SpawnParticles>,
+    mut rng: ResMut<ParticleRng>,
+    particle_query: Query<(), With<Particle>>,
+    settings: Res<ParticleSettings>,
+) {
+    let mut alive = particle_query.iter().count();
+
+    for event in events.iter() {
+        let def = &event.emitter;
+        let base_angle = event.direction.y.atan2(event.direction.x);
+        let count = event
+            .count
+            .min(settings.max_particles.saturating_sub(alive));
+
+        for i in 0..count {
+            let offset = if def.even_spread {
+                // A full circle would put the first and last particle on top of each other
+                let slots = if def.spread >= TAU {
+                    count
+                } else {
+                    count.max(2) - 1
+                };
+                def.spread * (i as f32 / slots.max(1) as f32) - def.spread / 2f32
+            } else {
+                rng.range(-def.spread / 2f32, def.spread / 2f32)
+            };
+            let angle = base_angle + offset;
+            let speed = rng.range(def.min_speed, def.max_speed);
+            let lifetime =
+                (def.lifetime + rng.range(-1f32, 1f32) * def.lifetime_variance).max(f32::EPSILON);
+
+            cmd.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::splat(def.size)),
+                        color: def.gradient.sample(0f32),
+                        ..Default::default()
+                    },
+                    texture: DEFAULT_IMAGE_HANDLE.typed(),
+                    transform: Transform::from_translation(event.position.extend(10f32)),
+                    ..Default::default()
+                },
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    age: 0f32,
+                    lifetime,
+                    gravity: def.gravity,
+                    drag: def.drag,
+                    gradient: def.gradient.clone(),
+                },
+            ));
+        }
+
+        alive += count;
+    }
+}
+
+fn update_particles(
+    mut cmd: Commands,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+
+    for (entity, mut particle, mut transform, mut sprite) in particle_query.iter_mut() {
+        particle.age += dt;
+        if particle.age >= particle.lifetime {
+            cmd.entity(entity).despawn();
+            continue;
+        }
+
+        let drag = (1f32 - particle.drag * dt).max(0f32);
+        particle.velocity.y += particle.gravity * dt;
+        particle.velocity *= drag;
+        transform.translation += (particle.velocity * dt).extend(0f32);
+
+        sprite.color = particle.gradient.sample(particle.age / particle.lifetime);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> ColorGradient {
+        ColorGradient::new(vec![
+            (1f32, Color::rgba(0f32, 0f32, 1f32, 0f32)),
+            (0f32, Color::rgba(1f32, 0f32, 0f32, 1f32)),
+        ])
+    }
+
+    #[test]
+    fn gradient_hits_its_keys() {
+        let gradient = gradient();
+
+        assert_eq!(gradient.sample(0f32), Color::rgba(1f32, 0f32, 0f32, 1f32));
+        assert_eq!(gradient.sample(1f32), Color::rgba(0f32, 0f32, 1f32, 0f32));
+        assert_eq!(
+            gradient.sample(0.5f32),
+            Color::rgba(0.5f32, 0f32, 0.5f32, 0.5f32)
+        );
+    }
+
+    #[test]
+    fn gradient_is_clamped_outside_its_keys() {
+        let gradient = gradient();
+
+        assert_eq!(gradient.sample(-1f32), gradient.sample(0f32));
+        assert_eq!(gradient.sample(2f32), gradient.sample(1f32));
+        assert_eq!(ColorGradient::new(Vec::new()).sample(0.5f32), Color::WHITE);
+    }
+
+    fn spawn(app: &mut App, count: usize) {
+        app.world.send_event(SpawnParticles {
+            emitter: EmitterDef::default(),
+            position: Vec2::ZERO,
+            direction: Vec2::Y,
+            count,
+        });
+    }
+
+    #[test]
+    fn particles_are_capped() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(ParticleSettings { max_particles: 10 })
+            .init_resource::<ParticleRng>()
+            .add_event::<SpawnParticles>()
+            .add_systems(Update, spawn_particles);
+        let count = |app: &mut App| {
+            app.world
+                .query_filtered::<(), With<Particle>>()
+                .iter(&app.world)
+                .count()
+        };
+
+        spawn(&mut app, 8);
+        app.update();
+        assert_eq!(count(&mut app), 8);
+
+        // Both events of a frame count towards the cap
+        spawn(&mut app, 1);
+        spawn(&mut app, 5);
+        app.update();
+        assert_eq!(count(&mut app), 10);
+
+        spawn(&mut app, 1);
+        app.update();
+        assert_eq!(count(&mut app), 10);
+    }
+}
@@ -1,8 +1,14 @@
 use bevy::{app::PluginGroupBuilder, prelude::*};
+use seldom_state::set::StateSet;
+
+use crate::menu::AppState;
 
 pub mod camera;
+pub mod facing;
+pub mod health;
 pub mod input;
 pub mod movement;
+pub mod particles;
 pub mod state_machine;
 pub mod visuals;
 
@@ -12,10 +18,13 @@ impl PluginGroup for PlayerPlugins {
     fn build(self) -> PluginGroupBuilder {
         PluginGroupBuilder::start::<Self>()
             .add(input::PlayerInputPlugin)
+            .add(facing::PlayerFacingPlugin)
             .add(state_machine::PlayerStateMachinePlugin)
             .add(movement::PlayerMovementPlugin)
+            .add(health::PlayerHealthPlugin)
             .add(camera::PlayerCameraPlugin)
             .add(visuals::PlayerVisualsPlugin)
+            .add(particles::PlayerParticlesPlugin)
     }
 }
 
@@ -31,12 +40,9 @@ impl Plugin for PlayerPlugin {
 #[derive(Component)]
 pub struct Player;
 
+/// The player is moved to the level's spawn point once the level has loaded
 pub fn init(mut cmd: Commands) {
-    cmd.spawn((
-        Player,
-        Name::from("Player"),
-        SpatialBundle::from_transform(Transform::from_xyz(0f32, 100f32, 0f32)),
-    ));
+    cmd.spawn((Player, Name::from("Player"), SpatialBundle::default()));
 }
 
 #[derive(SystemSet, Clone, Copy, PartialEq, Debug, Hash, Eq)]
@@ -80,6 +86,26 @@ impl Plugin for ConfigurePlayerSetsPlugin {
             )
                 .chain(),
         )
+        // Player systems are frozen while paused or in a menu
+        .configure_sets(
+            Update,
+            (
+                PlayerSet::PrePlayer.run_if(in_state(AppState::InGame)),
+                PlayerSet::Main.run_if(in_state(AppState::InGame)),
+                PlayerSet::Input.run_if(in_state(AppState::InGame)),
+                PlayerSet::StateMachine.run_if(in_state(AppState::InGame)),
+                PlayerSet::Camera.run_if(in_state(AppState::InGame)),
+                PlayerSet::Visuals.run_if(in_state(AppState::InGame)),
+                PlayerSet::Movement.run_if(in_state(AppState::InGame)),
+                PlayerSet::PostPlayer.run_if(in_state(AppState::InGame)),
+            ),
+        )
+        // Input keeps updating while paused, so the state machine would otherwise keep taking
+        // transitions and timed triggers would keep running
+        .configure_set(
+            PostUpdate,
+            StateSet::Transition.run_if(in_state(AppState::InGame)),
+        )
         .add_systems(
             Update,
             (
@@ -151,3 +177,61 @@ impl Plugin for ConfigurePlayerSetsPlugin {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{state_machine::states::*, *};
+    use seldom_state::prelude::*;
+
+    /// A player whose state machine leaves `FallingState` on the first transition it gets to take
+    fn app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            StateMachinePlugin,
+            ConfigurePlayerSetsPlugin,
+        ))
+        .add_state::<AppState>();
+
+        let player = app
+            .world
+            .spawn((
+                Player,
+                FallingState,
+                StateMachine::default().trans::<FallingState>(AlwaysTrigger, GroundedState::Idle),
+            ))
+            .id();
+
+        (app, player)
+    }
+
+    fn enter(app: &mut App, state: AppState) {
+        app.world.resource_mut::<NextState<AppState>>().set(state);
+        app.update();
+    }
+
+    #[test]
+    fn state_machine_is_frozen_while_paused() {
+        let (mut app, player) = app();
+        enter(&mut app, AppState::Paused);
+        for _ in 0..5 {
+            app.update();
+        }
+
+        assert!(app.world.get::<FallingState>(player).is_some());
+        assert!(app.world.get::<GroundedState>(player).is_none());
+    }
+
+    #[test]
+    fn state_machine_runs_in_game() {
+        let (mut app, player) = app();
+        enter(&mut app, AppState::InGame);
+        app.update();
+
+        assert!(app.world.get::<FallingState>(player).is_none());
+        assert_eq!(
+            app.world.get::<GroundedState>(player),
+            Some(&GroundedState::Idle)
+        );
+    }
+}
@@ -1,12 +1,14 @@
 use bevy::prelude::*;
 
-use super::PlayerStartupSet;
+use super::{facing::Facing, Player, PlayerSet, PlayerStartupSet};
 
 pub(super) struct PlayerCameraPlugin;
 
 impl Plugin for PlayerCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, init.in_set(PlayerStartupSet::Camera));
+        app.add_systems(Startup, init.in_set(PlayerStartupSet::Camera))
+            .add_systems(Update, follow_player.in_set(PlayerSet::Camera))
+            .register_type::<CameraFollow>();
     }
 }
 
@@ -15,6 +17,41 @@ fn init(mut cmd: Commands) {
         Camera2dBundle {
             ..Default::default()
         },
+        CameraFollow::default(),
         Name::from("Camera"),
     ));
 }
+
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct CameraFollow {
+    pub look_ahead: f32,
+    pub smoothing: f32,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            look_ahead: 80f32,
+            smoothing: 5f32,
+        }
+    }
+}
+
+fn follow_player(
+    player_query: Query<(&GlobalTransform, &Facing), With<Player>>,
+    mut camera_query: Query<(&mut Transform, &CameraFollow), Without<Player>>,
+    time: Res<Time>,
+) {
+    let Ok((player_transform, facing)) = player_query.get_single() else {
+        return;
+    };
+
+    for (mut transform, follow) in camera_query.iter_mut() {
+        let target =
+            player_transform.translation().truncate() + facing.direction() * follow.look_ahead;
+        let t = 1f32 - (-follow.smoothing * time.delta_seconds()).exp();
+
+        let position = transform.translation.truncate().lerp(target, t);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
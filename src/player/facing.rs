@@ -0,0 +1,172 @@
+use super::{input::InputAction, Player, PlayerSet, PlayerStartupSet};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+pub(super) struct PlayerFacingPlugin;
+
+impl Plugin for PlayerFacingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, init.in_set(PlayerStartupSet::Input))
+            .add_systems(Update, update_facing.in_set(PlayerSet::Input))
+            .register_type::<Facing>()
+            .register_type::<FacingHysteresis>();
+    }
+}
+
+fn init(mut cmd: Commands, player_query: Query<Entity, With<Player>>) {
+    cmd.entity(player_query.single())
+        .insert((Facing::Right, FacingHysteresis::default()));
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum Facing {
+    Left,
+    Right,
+}
+
+impl Facing {
+    pub fn sign(&self) -> f32 {
+        match self {
+            Facing::Left => -1f32,
+            Facing::Right => 1f32,
+        }
+    }
+
+    pub fn direction(&self) -> Vec2 {
+        Vec2::X * self.sign()
+    }
+
+    /// Facing for a signed value, or `None` if it is inside the dead zone
+    pub fn from_value(value: f32, dead_zone: f32) -> Option<Self> {
+        match value {
+            value if value > dead_zone => Some(Facing::Right),
+            value if value < -dead_zone => Some(Facing::Left),
+            _ => None,
+        }
+    }
+}
+
+/// How far input and velocity have to go past zero before the character turns around. Input wins
+/// over velocity, and when neither is past its threshold the facing is kept as is
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct FacingHysteresis {
+    pub input_threshold: f32,
+    pub velocity_threshold: f32,
+}
+
+impl Default for FacingHysteresis {
+    fn default() -> Self {
+        Self {
+            input_threshold: 0.5f32,
+            velocity_threshold: 20f32,
+        }
+    }
+}
+
+impl FacingHysteresis {
+    pub fn next_facing(&self, current: Facing, input: f32, velocity: f32) -> Facing {
+        Facing::from_value(input, self.input_threshold)
+            .or_else(|| {
+                // Only let velocity turn the character when there is no input holding it
+                (input.abs() <= f32::EPSILON)
+                    .then(|| Facing::from_value(velocity, self.velocity_threshold))
+                    .flatten()
+            })
+            .unwrap_or(current)
+    }
+}
+
+fn update_facing(
+    mut player_query: Query<
+        (
+            &mut Facing,
+            &FacingHysteresis,
+            &ActionState<InputAction>,
+            &Velocity,
+        ),
+        With<Player>,
+    >,
+) {
+    for (mut facing, hysteresis, input, vel) in player_query.iter_mut() {
+        let next = hysteresis.next_facing(*facing, input.value(InputAction::Run), vel.linvel.x);
+
+        if next != *facing {
+            *facing = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_input_keeps_facing() {
+        let hysteresis = FacingHysteresis::default();
+
+        // Zero input used to turn the character left
+        assert_eq!(
+            hysteresis.next_facing(Facing::Right, 0f32, 0f32),
+            Facing::Right
+        );
+        assert_eq!(
+            hysteresis.next_facing(Facing::Left, 0f32, 0f32),
+            Facing::Left
+        );
+    }
+
+    #[test]
+    fn opposite_input_flips_past_threshold() {
+        let hysteresis = FacingHysteresis::default();
+        let threshold = hysteresis.input_threshold;
+
+        assert_eq!(
+            hysteresis.next_facing(Facing::Right, -threshold, 0f32),
+            Facing::Right
+        );
+        assert_eq!(
+            hysteresis.next_facing(Facing::Right, -threshold - 0.1f32, 0f32),
+            Facing::Left
+        );
+        assert_eq!(
+            hysteresis.next_facing(Facing::Left, threshold, 0f32),
+            Facing::Left
+        );
+        assert_eq!(
+            hysteresis.next_facing(Facing::Left, threshold + 0.1f32, 0f32),
+            Facing::Right
+        );
+    }
+
+    #[test]
+    fn input_under_threshold_holds_facing_against_velocity() {
+        let hysteresis = FacingHysteresis::default();
+        let velocity = -hysteresis.velocity_threshold * 2f32;
+
+        assert_eq!(
+            hysteresis.next_facing(Facing::Right, 0.1f32, velocity),
+            Facing::Right
+        );
+    }
+
+    #[test]
+    fn velocity_alone_flips_facing() {
+        let hysteresis = FacingHysteresis::default();
+        let threshold = hysteresis.velocity_threshold;
+
+        // Drifting backwards in the air without input
+        assert_eq!(
+            hysteresis.next_facing(Facing::Right, 0f32, -threshold),
+            Facing::Right
+        );
+        assert_eq!(
+            hysteresis.next_facing(Facing::Right, 0f32, -threshold - 1f32),
+            Facing::Left
+        );
+        assert_eq!(
+            hysteresis.next_facing(Facing::Left, 0f32, threshold + 1f32),
+            Facing::Right
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use super::{state_machine::states::DeadState, Player, PlayerSet, PlayerStartupSet};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use seldom_state::prelude::*;
+
+use crate::level::{respawn_point, ActiveCheckpoint, Hazard, LevelRooms, LevelState, SpawnPoint};
+
+pub(super) struct PlayerHealthPlugin;
+
+impl Plugin for PlayerHealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, init.in_set(PlayerStartupSet::PostPlayer))
+            .add_systems(
+                Update,
+                (
+                    track_hazard_contacts,
+                    (damage_from_hazards, kill_out_of_bounds).run_if(in_state(LevelState::Playing)),
+                    start_death,
+                    play_death,
+                )
+                    .chain()
+                    .in_set(PlayerSet::PostPlayer),
+            )
+            .register_type::<Health>();
+    }
+}
+
+fn init(mut cmd: Commands, player_query: Query<Entity, With<Player>>) {
+    cmd.entity(player_query.single())
+        .insert((Health::new(3, 1.2f32, 1f32), HazardContacts::default()));
+}
+
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+    /// Runs after every hit. Hazards can't do damage until it has finished
+    pub invulnerability: Timer,
+    /// How long dying plays out before the character respawns
+    pub death: Timer,
+}
+
+impl Health {
+    pub fn new(max: u32, invulnerability_time: f32, death_time: f32) -> Self {
+        let mut invulnerability = Timer::from_seconds(invulnerability_time, TimerMode::Once);
+        invulnerability.tick(Duration::from_secs_f32(invulnerability_time));
+
+        Self {
+            current: max,
+            max,
+            invulnerability,
+            death: Timer::from_seconds(death_time, TimerMode::Once),
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+
+    pub fn invulnerable(&self) -> bool {
+        !self.invulnerability.finished()
+    }
+}
+
+/// Hazards the character is currently inside, kept up to date from collision events
+#[derive(Component, Clone, Debug, Default)]
+pub struct HazardContacts(pub Vec<Entity>);
+
+fn track_hazard_contacts(
+    mut collisions: EventReader<CollisionEvent>,
+    mut player_query: Query<&mut HazardContacts>,
+    hazard_query: Query<(), With<Hazard>>,
+) {
+    for event in collisions.iter() {
+        let (a, b, started) = match *event {
+            CollisionEvent::Started(a, b, _) => (a, b, true),
+            CollisionEvent::Stopped(a, b, _) => (a, b, false),
+        };
+
+        for (player, hazard) in [(a, b), (b, a)] {
+            if !hazard_query.contains(hazard) {
+                continue;
+            }
+            let Ok(mut contacts) = player_query.get_mut(player) else {
+                continue;
+            };
+
+            if started {
+                contacts.0.push(hazard);
+            } else {
+                contacts.0.retain(|contact| *contact != hazard);
+            }
+        }
+    }
+}
+
+fn damage_from_hazards(
+    mut player_query: Query<
+        (&mut Health, &mut Velocity, &HazardContacts, &Transform),
+        Without<DeadState>,
+    >,
+    hazard_query: Query<(&Hazard, &GlobalTransform)>,
+    time: Res<Time>,
+) {
+    for (mut health, mut vel, contacts, transform) in player_query.iter_mut() {
+        health.invulnerability.tick(time.delta());
+        if health.invulnerable() {
+            continue;
+        }
+
+        // Standing in several hazards at once only hurts as much as the worst of them
+        let Some((hazard, hazard_transform)) = hazard_query
+            .iter_many(&contacts.0)
+            .max_by_key(|(hazard, _)| hazard.damage)
+        else {
+            continue;
+        };
+
+        health.current = health.current.saturating_sub(hazard.damage);
+        health.invulnerability.reset();
+
+        // Always knock the character a bit upwards so it gets off hazards on the floor
+        let away = (transform.translation - hazard_transform.translation()).truncate();
+        let direction = Vec2::new(away.x.signum(), away.y.max(0f32) + 1f32).normalize();
+        vel.linvel = direction * hazard.knockback;
+    }
+}
+
+/// Distance below the bottom of the level the character dies at
+pub const KILL_DEPTH: f32 = 1000f32;
+
+/// Kills characters that fell far below the level
+fn kill_out_of_bounds(
+    mut player_query: Query<(&mut Health, &Transform), Without<DeadState>>,
+    rooms: Res<LevelRooms>,
+) {
+    // Levels in a world of rooms can be anywhere in it
+    let floor = rooms.bounds.map_or(0f32, |bounds| bounds.min.y) - KILL_DEPTH;
+    for (mut health, transform) in player_query.iter_mut() {
+        if transform.translation.x.abs() > 100000f32
+            || transform.translation.y < floor
+            || transform.translation.y > 50000f32
+        {
+            health.current = 0;
+        }
+    }
+}
+
+fn start_death(mut player_query: Query<&mut Health, Added<DeadState>>) {
+    for mut health in player_query.iter_mut() {
+        health.death.reset();
+    }
+}
+
+/// Holds the character in place while the death plays out, then respawns it at the last
+/// checkpoint and finishes the [`DeadState`]
+fn play_death(
+    mut cmd: Commands,
+    mut player_query: Query<(Entity, &mut Health, &mut Transform, &mut Velocity), With<DeadState>>,
+    spawn: Res<SpawnPoint>,
+    checkpoint: Res<ActiveCheckpoint>,
+    time: Res<Time>,
+) {
+    for (entity, mut health, mut transform, mut vel) in player_query.iter_mut() {
+        vel.linvel = Vec2::ZERO;
+        vel.angvel = 0f32;
+
+        health.death.tick(time.delta());
+        if !health.death.just_finished() {
+            continue;
+        }
+
+        transform.translation = respawn_point(&spawn, &checkpoint).extend(transform.translation.z);
+        transform.rotation = Quat::default();
+        health.current = health.max;
+        health.invulnerability.reset();
+
+        cmd.entity(entity).insert(Done::Success);
+    }
+}
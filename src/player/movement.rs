@@ -1,13 +1,26 @@
-use std::time::Duration;
+use std::{path::Path, time::Duration};
 
 use super::{input::InputAction, state_machine::states::*, Player, PlayerSet, PlayerStartupSet};
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+};
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use crate::level::GroundMaterial;
+
+pub mod jump_arc;
+pub mod preset;
 pub mod sub_components;
 use leafwing_input_manager::prelude::ActionState;
+use preset::PresetError;
 use sub_components::*;
 
+/// Horizontal speed is multiplied by this on takeoff. Horizontal movement caps it back to the top
+/// running speed, so it only speeds up jumps started below top speed
+pub const TAKEOFF_SPEED_BOOST: f32 = 1.1f32;
+
 pub(super) struct PlayerMovementPlugin;
 
 impl Plugin for PlayerMovementPlugin {
@@ -15,41 +28,29 @@ impl Plugin for PlayerMovementPlugin {
         app.add_systems(Startup, init.in_set(PlayerStartupSet::Movement))
             .add_systems(
                 Update,
-                (controller_jump_variables, jump, fall, horizontal_movement)
+                (
+                    controller_jump_variables,
+                    jump,
+                    fall,
+                    horizontal_movement,
+                    sync_gravity_scale,
+                )
                     .chain()
                     .in_set(PlayerSet::Movement),
             )
-            .add_systems(Update, respawn.run_if(should_respawn))
-            .add_plugins(sub_components::MovementSubComponentsPlugin)
+            .add_plugins((
+                sub_components::MovementSubComponentsPlugin,
+                preset::CharacterPresetPlugin,
+            ))
             .register_type::<CharacterController>();
     }
 }
 
-fn should_respawn(player_query: Query<&Transform, With<Player>>) -> bool {
-    match player_query.get_single() {
-        Ok(transform) => {
-            transform.translation.x.abs() > 100000f32
-                || transform.translation.y < -1000f32
-                || transform.translation.y > 50000f32
-        }
-        _ => false,
-    }
-}
-
-fn respawn(mut player_query: Query<(&mut Transform, &mut Velocity), With<Player>>) {
-    let (mut transform, mut vel) = player_query.single_mut();
+fn init(mut cmd: Commands, player_query: Query<Entity, With<Player>>) {
+    // Replaced by the preset file once the asset has loaded
+    let controller = CharacterControllerBuilder::default().build();
 
-    transform.translation = Vec3::ZERO;
-    transform.rotation = Quat::default();
-    vel.linvel = Vec2::ZERO;
-    vel.angvel = 0f32;
-}
-
-fn init(mut cmd: Commands, player_query: Query<(Entity, &Sprite), With<Player>>) {
-    let (entity, sprite) = player_query.single();
-    let size = sprite.custom_size.unwrap_or(Vec2::ONE * 25f32);
-
-    cmd.entity(entity).insert((
+    cmd.entity(player_query.single()).insert((
         Friction {
             coefficient: 0f32,
             combine_rule: CoefficientCombineRule::Min,
@@ -61,28 +62,19 @@ fn init(mut cmd: Commands, player_query: Query<(Entity, &Sprite), With<Player>>)
         RigidBody::Dynamic,
         ColliderMassProperties::Density(2f32),
         Velocity::default(),
-        Collider::cuboid(size.x / 2f32, size.y / 2f32),
+        controller.collider(),
+        GravityScale(controller.gravity_scale),
         Ccd::enabled(),
         LockedAxes::ROTATION_LOCKED,
-        CharacterControllerBuilder {
-            size,
-            jump_force: 450f32,
-            coyote_time: 0.175f32,
-            jump_buffer_time: 0.2f32,
-            jump_release_multi: 0.3f32,
-            wall_jump_force: Vec2::new(130f32, 300f32),
-
-            max_move_speed: 250f32,
-            acceleration_force: 2500f32,
-            decceleration_force: 1500f32,
-            turnaround_multi: 1.5f32,
-
-            air_control: 0.4f32,
-        }
-        .build(),
+        controller,
     ));
 }
 
+/// Everything needed to build a [`CharacterController`]. Presets are stored as RON files of this,
+/// and loaded as assets by the [`preset`] module, which reports missing or invalid fields by name
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, TypeUuid, TypePath)]
+#[serde(deny_unknown_fields)]
+#[uuid = "5d6b8c3e-2f4a-4e1b-9c7d-8a3f1e6b2d40"]
 pub struct CharacterControllerBuilder {
     pub size: Vec2,
 
@@ -98,10 +90,94 @@ pub struct CharacterControllerBuilder {
     pub turnaround_multi: f32,
 
     pub air_control: f32,
+    pub grounded_delay: f32,
+    /// How far past the collider the surface checker looks for ground
+    #[serde(default = "CharacterControllerBuilder::default_cast_distance")]
+    pub cast_distance: f32,
+
+    /// Multiplier of the world's gravity for this character
+    #[serde(default = "CharacterControllerBuilder::default_gravity_scale")]
+    pub gravity_scale: f32,
+}
+
+impl Default for CharacterControllerBuilder {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(25f32, 50f32),
+
+            jump_force: 450f32,
+            coyote_time: 0.175f32,
+            jump_buffer_time: 0.2f32,
+            jump_release_multi: 0.3f32,
+            wall_jump_force: Vec2::new(130f32, 300f32),
+
+            max_move_speed: 250f32,
+            acceleration_force: 2500f32,
+            decceleration_force: 1500f32,
+            turnaround_multi: 1.5f32,
+
+            air_control: 0.4f32,
+            grounded_delay: 0.1f32,
+            cast_distance: Self::default_cast_distance(),
+
+            gravity_scale: Self::default_gravity_scale(),
+        }
+    }
 }
 
 impl CharacterControllerBuilder {
+    pub fn default_gravity_scale() -> f32 {
+        1f32
+    }
+
+    pub fn default_cast_distance() -> f32 {
+        SurfaceGroundedChecker::default().cast_distance
+    }
+
+    /// The builder that would build `controller`, ignoring its runtime state
+    pub fn from_controller(controller: &CharacterController) -> Self {
+        Self {
+            size: controller.size,
+
+            jump_force: controller.jump_force,
+            coyote_time: controller.coyote_timer.duration().as_secs_f32(),
+            jump_buffer_time: controller.jump_buffer_timer.duration().as_secs_f32(),
+            jump_release_multi: controller.jump_release_multi,
+            wall_jump_force: controller.wall_jump_force,
+
+            max_move_speed: controller.max_move_speed,
+            acceleration_force: controller.acceleration_force,
+            decceleration_force: controller.decceleration_force,
+            turnaround_multi: controller.turnaround_multi,
+
+            air_control: controller.air_control,
+            grounded_delay: controller.surface_checker.grounded_delay.as_secs_f32(),
+            cast_distance: controller.surface_checker.cast_distance,
+
+            gravity_scale: controller.gravity_scale,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PresetError> {
+        let source = std::fs::read_to_string(path).map_err(PresetError::Io)?;
+        Self::from_ron(&source)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PresetError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(PresetError::Io)?;
+        }
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(PresetError::Serialize)?;
+        std::fs::write(path, source).map_err(PresetError::Io)
+    }
+
     pub fn build(self) -> CharacterController {
+        let mut surface_checker =
+            SurfaceGroundedChecker::new(Duration::from_secs_f32(self.grounded_delay));
+        surface_checker.cast_distance = self.cast_distance;
+
         CharacterController {
             size: self.size,
 
@@ -122,7 +198,9 @@ impl CharacterControllerBuilder {
 
             air_control: self.air_control,
 
-            surface_checker: SurfaceGroundedChecker::default(),
+            surface_checker,
+
+            gravity_scale: self.gravity_scale,
         }
     }
 }
@@ -145,6 +223,59 @@ pub struct CharacterController {
 
     pub surface_checker: SurfaceGroundedChecker,
     pub size: Vec2,
+
+    /// Kept in sync with the character's [`GravityScale`]
+    pub gravity_scale: f32,
+}
+
+impl CharacterController {
+    /// Takes every parameter from `builder`, keeping runtime state like the elapsed time of the
+    /// timers, `has_released_jump` and the surface contacts
+    pub fn apply_builder(&mut self, builder: &CharacterControllerBuilder) {
+        self.size = builder.size;
+
+        self.jump_force = builder.jump_force;
+        self.coyote_timer
+            .set_duration(Duration::from_secs_f32(builder.coyote_time));
+        self.jump_buffer_timer
+            .set_duration(Duration::from_secs_f32(builder.jump_buffer_time));
+        self.jump_release_multi = builder.jump_release_multi;
+        self.wall_jump_force = builder.wall_jump_force;
+
+        self.max_move_speed = builder.max_move_speed;
+        self.acceleration_force = builder.acceleration_force;
+        self.decceleration_force = builder.decceleration_force;
+        self.turnaround_multi = builder.turnaround_multi;
+
+        self.air_control = builder.air_control;
+        self.surface_checker.grounded_delay = Duration::from_secs_f32(builder.grounded_delay);
+        self.surface_checker.cast_distance = builder.cast_distance;
+
+        self.gravity_scale = builder.gravity_scale;
+    }
+
+    pub fn collider(&self) -> Collider {
+        Collider::cuboid(self.size.x / 2f32, self.size.y / 2f32)
+    }
+
+    pub fn ground_entity(&self) -> Option<Entity> {
+        self.surface_checker
+            .contact(&Surface::Bottom)
+            .map(|contact| contact.entity)
+    }
+
+    pub fn ground_normal(&self) -> Option<Vec2> {
+        self.surface_checker
+            .contact(&Surface::Bottom)
+            .map(|contact| contact.normal)
+    }
+
+    /// Material of the ground the character stands on, or the normal material in the air
+    pub fn ground_material(&self) -> GroundMaterial {
+        self.surface_checker
+            .contact(&Surface::Bottom)
+            .map_or(GroundMaterial::NORMAL, |contact| contact.material)
+    }
 }
 
 fn horizontal_movement(
@@ -154,17 +285,21 @@ fn horizontal_movement(
             &mut Velocity,
             &ActionState<InputAction>,
         ),
-        With<Player>,
+        Without<DeadState>,
     >,
     time: Res<Time>,
 ) {
-    let (controller, mut vel, input) = player_query.single_mut();
+    let Ok((controller, mut vel, input)) = player_query.get_single_mut() else {
+        return;
+    };
 
     let move_val = input.value(InputAction::Run);
 
     let grounded = controller
         .surface_checker
         .surface_touching_ground(&Surface::Bottom);
+    let material = controller.ground_material();
+    let max_move_speed = controller.max_move_speed * material.max_speed_multi;
 
     let air_control_multi = if !grounded {
         controller.air_control
@@ -178,13 +313,14 @@ fn horizontal_movement(
     };
 
     let add_val = controller.acceleration_force
+        * material.acceleration_multi
         * time.delta_seconds()
         * move_val
         * turnaround_multi
         * air_control_multi;
 
-    vel.linvel.x += if (vel.linvel.x + add_val).abs() > controller.max_move_speed {
-        (controller.max_move_speed - vel.linvel.x.abs()).min(-5f32) * add_val.signum()
+    vel.linvel.x += if (vel.linvel.x + add_val).abs() > max_move_speed {
+        (max_move_speed - vel.linvel.x.abs()).min(-5f32) * add_val.signum()
     } else {
         add_val
     };
@@ -194,7 +330,10 @@ fn horizontal_movement(
     }
 
     // Deccelerate
-    let sub_val = controller.decceleration_force * time.delta_seconds() * vel.linvel.x.signum();
+    let sub_val = controller.decceleration_force
+        * material.decceleration_multi
+        * time.delta_seconds()
+        * vel.linvel.x.signum();
 
     if (vel.linvel.x - sub_val).signum() != vel.linvel.x.signum() {
         vel.linvel.x = 0f32;
@@ -272,7 +411,9 @@ fn jump(
         _ => return,
     };
 
-    controller.has_released_jump = false;
+    // Bounces aren't held, so they can't be cut short by releasing jump
+    let material = controller.ground_material();
+    controller.has_released_jump = material.bounce.is_some();
     controller
         .jump_buffer_timer
         .tick(Duration::from_secs_f32(1000f32));
@@ -280,10 +421,23 @@ fn jump(
         .coyote_timer
         .tick(Duration::from_secs_f32(1000f32));
 
-    vel.linvel.y = controller.jump_force * force_multi;
+    vel.linvel.y = controller.jump_force * force_multi * material.jump_force_multi;
     if vel.linvel.x.abs() > 0f32 {
-        vel.linvel.x *= 1.1f32
+        vel.linvel.x *= TAKEOFF_SPEED_BOOST
     }
 
     grounded_delay_event.send(ActivateGroundedDelay(Surface::Bottom));
 }
+
+fn sync_gravity_scale(
+    mut player_query: Query<
+        (&CharacterController, &mut GravityScale),
+        Changed<CharacterController>,
+    >,
+) {
+    for (controller, mut gravity_scale) in player_query.iter_mut() {
+        if gravity_scale.0 != controller.gravity_scale {
+            gravity_scale.0 = controller.gravity_scale;
+        }
+    }
+}
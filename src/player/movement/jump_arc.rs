@@ -0,0 +1,224 @@
+use std::fmt;
+
+use super::CharacterControllerBuilder;
+use crate::GRAVITY;
+
+/// Shape of a held jump from flat ground at top running speed, in pixels and seconds. At top speed
+/// the [`TAKEOFF_SPEED_BOOST`](super::TAKEOFF_SPEED_BOOST) is capped away on the frame it's
+/// applied, and air control doesn't matter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JumpArc {
+    /// Height of the apex above the takeoff point
+    pub height: f32,
+    pub time_to_apex: f32,
+    /// Distance covered before landing back at the takeoff height
+    pub distance: f32,
+}
+
+impl JumpArc {
+    pub fn new(height: f32, time_to_apex: f32, distance: f32) -> Self {
+        Self {
+            height,
+            time_to_apex,
+            distance,
+        }
+    }
+
+    /// Downwards acceleration during the jump
+    pub fn gravity(&self) -> f32 {
+        2f32 * self.height / (self.time_to_apex * self.time_to_apex)
+    }
+
+    /// Upwards speed on takeoff, which is what `jump_force` sets
+    pub fn jump_force(&self) -> f32 {
+        2f32 * self.height / self.time_to_apex
+    }
+
+    pub fn air_time(&self) -> f32 {
+        2f32 * self.time_to_apex
+    }
+
+    pub fn run_speed(&self) -> f32 {
+        self.distance / self.air_time()
+    }
+
+    /// Height above the takeoff point `time` seconds into the jump
+    pub fn height_at(&self, time: f32) -> f32 {
+        self.jump_force() * time - self.gravity() * time * time / 2f32
+    }
+
+    /// Time the jump comes back down to `height` above the takeoff point, if it gets that high
+    pub fn landing_time(&self, height: f32) -> Option<f32> {
+        (height <= self.height)
+            .then(|| self.time_to_apex + (2f32 * (self.height - height) / self.gravity()).sqrt())
+    }
+
+    /// Distance covered by the time the jump comes back down to `height` above the takeoff point
+    pub fn distance_at(&self, height: f32) -> Option<f32> {
+        self.landing_time(height)
+            .map(|time| time * self.run_speed())
+    }
+}
+
+impl fmt::Display for JumpArc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}px high, apex after {:.3}s, {:.1}px far",
+            self.height, self.time_to_apex, self.distance
+        )
+    }
+}
+
+impl CharacterControllerBuilder {
+    /// The default preset, with jump force, gravity scale and run speed computed from `arc`
+    pub fn from_jump_arc(arc: JumpArc) -> Self {
+        Self::default().with_jump_arc(arc)
+    }
+
+    /// The default preset, with jump force and gravity scale computed to reach `height` after
+    /// `time_to_apex` seconds
+    pub fn from_jump_height(height: f32, time_to_apex: f32) -> Self {
+        Self::default().with_jump_height(height, time_to_apex)
+    }
+
+    pub fn with_jump_arc(self, arc: JumpArc) -> Self {
+        Self {
+            max_move_speed: arc.run_speed(),
+            ..self.with_jump_height(arc.height, arc.time_to_apex)
+        }
+    }
+
+    pub fn with_jump_height(self, height: f32, time_to_apex: f32) -> Self {
+        // Distance doesn't affect the jump force or gravity
+        let arc = JumpArc::new(height, time_to_apex, 0f32);
+        Self {
+            jump_force: arc.jump_force(),
+            gravity_scale: arc.gravity() / GRAVITY,
+            ..self
+        }
+    }
+
+    /// The arc this preset jumps in, the inverse of [`Self::from_jump_arc`]
+    pub fn jump_arc(&self) -> JumpArc {
+        let gravity = GRAVITY * self.gravity_scale;
+        let time_to_apex = self.jump_force / gravity;
+
+        JumpArc {
+            height: self.jump_force * self.jump_force / (2f32 * gravity),
+            time_to_apex,
+            distance: self.max_move_speed * 2f32 * time_to_apex,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::movement::CharacterController;
+    use bevy::prelude::*;
+    use bevy_rapier2d::prelude::*;
+
+    const STEP: f32 = 1f32 / 60f32;
+
+    fn arc() -> JumpArc {
+        JumpArc::new(120f32, 0.4f32, 300f32)
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn preset_round_trips_the_arc() {
+        let arc = arc();
+        let round_trip = CharacterControllerBuilder::from_jump_arc(arc).jump_arc();
+
+        assert_close(round_trip.height, arc.height, 1e-3);
+        assert_close(round_trip.time_to_apex, arc.time_to_apex, 1e-5);
+        assert_close(round_trip.distance, arc.distance, 1e-3);
+    }
+
+    /// Heights of a body launched like `controller` jumps, after each physics step until it comes
+    /// back down to where it started
+    fn simulate_jump(controller: &CharacterController) -> Vec<f32> {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100f32),
+        ))
+        .insert_resource(RapierConfiguration {
+            gravity: Vec2::new(0f32, -GRAVITY),
+            timestep_mode: TimestepMode::Fixed {
+                dt: STEP,
+                substeps: 1,
+            },
+            ..Default::default()
+        });
+        let body = app
+            .world
+            .spawn((
+                TransformBundle::default(),
+                RigidBody::Dynamic,
+                controller.collider(),
+                GravityScale(controller.gravity_scale),
+                Velocity::linear(Vec2::new(controller.max_move_speed, controller.jump_force)),
+                LockedAxes::ROTATION_LOCKED,
+            ))
+            .id();
+
+        let mut heights = Vec::new();
+        loop {
+            app.update();
+            let height = app.world.get::<Transform>(body).unwrap().translation.y;
+            if height <= 0f32 || heights.len() as f32 * STEP > 10f32 {
+                return heights;
+            }
+            heights.push(height);
+        }
+    }
+
+    #[test]
+    fn physics_jump_matches_the_arc() {
+        let builder = CharacterControllerBuilder::from_jump_arc(arc());
+        let arc = builder.jump_arc();
+        let heights = simulate_jump(&builder.build());
+
+        let (apex_step, apex) = heights
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        // Within one step of movement of the exact curve
+        assert_close(apex, arc.height, arc.jump_force() * STEP);
+        assert_close((apex_step + 1) as f32 * STEP, arc.time_to_apex, STEP);
+        // Back at the takeoff height on the step after the last one above it
+        assert_close((heights.len() + 1) as f32 * STEP, arc.air_time(), STEP);
+    }
+
+    #[test]
+    fn arc_distance_is_covered_by_the_landing() {
+        let arc = arc();
+
+        assert_close(
+            arc.distance_at(0f32).unwrap(),
+            arc.distance,
+            arc.distance * 1e-5,
+        );
+    }
+
+    #[test]
+    fn jump_height_keeps_run_speed() {
+        let controller = CharacterControllerBuilder::default();
+        let tuned = controller.clone().with_jump_height(200f32, 0.5f32);
+
+        assert_eq!(tuned.max_move_speed, controller.max_move_speed);
+        assert_close(tuned.jump_arc().height, 200f32, 1e-3);
+        assert_close(tuned.jump_arc().time_to_apex, 0.5f32, 1e-5);
+    }
+}
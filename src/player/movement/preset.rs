@@ -0,0 +1,271 @@
+use bevy::{
+    asset::{AssetLoader, FileAssetIo, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use ron::Value;
+use std::{fmt, path::PathBuf};
+
+use super::{CharacterController, CharacterControllerBuilder};
+use crate::player::{Player, PlayerStartupSet};
+
+/// Preset the player is built from, relative to the assets folder. Edits to the file are applied
+/// to the running game
+pub const PLAYER_PRESET_PATH: &str = "characters/default.preset.ron";
+
+pub(super) struct CharacterPresetPlugin;
+
+impl Plugin for CharacterPresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<CharacterControllerBuilder>()
+            .init_asset_loader::<CharacterPresetLoader>()
+            .add_systems(
+                Startup,
+                load_player_preset.in_set(PlayerStartupSet::Movement),
+            )
+            .add_systems(Update, apply_player_preset);
+    }
+}
+
+/// Where a preset path relative to the assets folder is on disk, found the same way as the asset
+/// server does
+pub fn preset_file_path(path: &str) -> PathBuf {
+    FileAssetIo::get_base_path().join("assets").join(path)
+}
+
+/// Reads the player's preset from disk, without the asset server but from the same folder,
+/// falling back to the default
+pub fn read_player_preset() -> CharacterControllerBuilder {
+    let path = preset_file_path(PLAYER_PRESET_PATH);
+    CharacterControllerBuilder::load(&path).unwrap_or_else(|err| {
+        println!(
+            "Could not load character preset {}, using the default. {err}",
+            path.display()
+        );
+        CharacterControllerBuilder::default()
+    })
+}
+
+/// Handle to the preset the player's [`CharacterController`] follows
+#[derive(Resource, Clone, Debug)]
+pub struct PlayerPreset(pub Handle<CharacterControllerBuilder>);
+
+#[derive(Debug)]
+pub enum PresetError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// The preset isn't a struct of named fields
+    NotAStruct,
+    MissingField(&'static str),
+    InvalidField(String, ron::Error),
+    UnknownField(String),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::Io(err) => write!(f, "Could not access character preset: {err}"),
+            PresetError::Parse(err) => write!(f, "Invalid character preset: {err}"),
+            PresetError::Serialize(err) => write!(f, "Could not serialize character preset: {err}"),
+            PresetError::NotAStruct => {
+                write!(
+                    f,
+                    "Invalid character preset: expected a struct of named fields"
+                )
+            }
+            PresetError::MissingField(field) => {
+                write!(f, "Invalid character preset: missing field `{field}`")
+            }
+            PresetError::InvalidField(field, err) => {
+                write!(f, "Invalid character preset: field `{field}`: {err}")
+            }
+            PresetError::UnknownField(field) => {
+                write!(f, "Invalid character preset: unknown field `{field}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+impl CharacterControllerBuilder {
+    /// Parses a preset, naming the field that is missing, unknown or has a wrong value
+    pub fn from_ron(source: &str) -> Result<Self, PresetError> {
+        let err = match ron::de::from_str(source) {
+            Ok(builder) => return Ok(builder),
+            Err(err) => err,
+        };
+        match err.code {
+            ron::Error::MissingStructField { field, .. } => {
+                return Err(PresetError::MissingField(field))
+            }
+            ron::Error::NoSuchStructField { found, .. } => {
+                return Err(PresetError::UnknownField(found))
+            }
+            _ => {}
+        }
+
+        // Serde doesn't say which field a wrong value belongs to, so each field is tried on its
+        // own in an otherwise valid preset
+        let fields = match ron::de::from_str(source) {
+            Ok(Value::Map(fields)) => fields,
+            Ok(_) => return Err(PresetError::NotAStruct),
+            Err(_) => return Err(PresetError::Parse(err)),
+        };
+        let defaults = ron::to_string(&Self::default()).map_err(PresetError::Serialize)?;
+        let Ok(Value::Map(defaults)) = ron::de::from_str::<Value>(&defaults) else {
+            return Err(PresetError::Parse(err));
+        };
+        for (name, value) in fields.iter() {
+            let mut preset = defaults.clone();
+            preset.insert(name.clone(), value.clone());
+            if let Err(field_err) = Value::Map(preset).into_rust::<Self>() {
+                return Err(PresetError::InvalidField(field_name(name), field_err));
+            }
+        }
+        Err(PresetError::Parse(err))
+    }
+}
+
+fn field_name(name: &Value) -> String {
+    match name {
+        Value::String(name) => name.clone(),
+        name => format!("{name:?}"),
+    }
+}
+
+#[derive(Default)]
+pub struct CharacterPresetLoader;
+
+impl AssetLoader for CharacterPresetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let source = std::str::from_utf8(bytes)?;
+            let builder = CharacterControllerBuilder::from_ron(source)?;
+            load_context.set_default_asset(LoadedAsset::new(builder));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["preset.ron"]
+    }
+}
+
+fn load_player_preset(mut cmd: Commands, asset_server: Res<AssetServer>) {
+    cmd.insert_resource(PlayerPreset(asset_server.load(PLAYER_PRESET_PATH)));
+}
+
+/// Rebuilds the player's controller whenever its preset is loaded or changes on disk
+fn apply_player_preset(
+    mut cmd: Commands,
+    mut events: EventReader<AssetEvent<CharacterControllerBuilder>>,
+    presets: Res<Assets<CharacterControllerBuilder>>,
+    preset: Res<PlayerPreset>,
+    mut player_query: Query<(Entity, &mut CharacterController), With<Player>>,
+) {
+    let changed = events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => *handle == preset.0,
+        AssetEvent::Removed { .. } => false,
+    });
+    if !changed {
+        return;
+    }
+
+    let (Some(builder), Ok((player, mut controller))) =
+        (presets.get(&preset.0), player_query.get_single_mut())
+    else {
+        return;
+    };
+
+    let resized = controller.size != builder.size;
+    controller.apply_builder(builder);
+    if resized {
+        cmd.entity(player).insert(controller.collider());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT_PRESET: &str = include_str!("../../../assets/characters/default.preset.ron");
+
+    #[test]
+    fn default_preset_file_matches_default() {
+        assert_eq!(
+            CharacterControllerBuilder::from_ron(DEFAULT_PRESET).unwrap(),
+            CharacterControllerBuilder::default()
+        );
+    }
+
+    #[test]
+    fn missing_field_is_named() {
+        let source = DEFAULT_PRESET.replace("jump_force: 450.0,", "");
+
+        assert!(matches!(
+            CharacterControllerBuilder::from_ron(&source),
+            Err(PresetError::MissingField("jump_force"))
+        ));
+    }
+
+    #[test]
+    fn invalid_field_is_named() {
+        let source = DEFAULT_PRESET.replace("size: (25.0, 50.0),", "size: \"big\",");
+
+        assert!(matches!(
+            CharacterControllerBuilder::from_ron(&source),
+            Err(PresetError::InvalidField(field, _)) if field == "size"
+        ));
+    }
+
+    #[test]
+    fn unknown_field_is_named() {
+        let source =
+            DEFAULT_PRESET.replace("air_control: 0.4,", "air_control: 0.4,\n    air_jumps: 2,");
+
+        assert!(matches!(
+            CharacterControllerBuilder::from_ron(&source),
+            Err(PresetError::UnknownField(field)) if field == "air_jumps"
+        ));
+    }
+
+    #[test]
+    fn controller_gives_back_its_builder() {
+        let builder = CharacterControllerBuilder {
+            cast_distance: 5f32,
+            grounded_delay: 0.25f32,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            CharacterControllerBuilder::from_controller(&builder.clone().build()),
+            builder
+        );
+    }
+
+    #[test]
+    fn applying_a_preset_keeps_runtime_state() {
+        let mut controller = CharacterControllerBuilder::default().build();
+        controller
+            .coyote_timer
+            .tick(std::time::Duration::from_secs_f32(0.1f32));
+        controller.has_released_jump = false;
+
+        controller.apply_builder(&CharacterControllerBuilder {
+            coyote_time: 0.5f32,
+            jump_force: 600f32,
+            ..Default::default()
+        });
+
+        assert_eq!(controller.jump_force, 600f32);
+        assert_eq!(controller.coyote_timer.duration().as_secs_f32(), 0.5f32);
+        assert_eq!(controller.coyote_timer.elapsed_secs(), 0.1f32);
+        assert!(!controller.has_released_jump);
+    }
+}
@@ -1,12 +1,13 @@
-use bevy::{prelude::*, render::texture::DEFAULT_IMAGE_HANDLE};
+use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use std::{collections::HashMap, hash::Hash};
+use std::{collections::HashMap, hash::Hash, time::Duration};
 
+#[cfg(feature = "debug")]
+use crate::debug::{overlay_enabled, DebugOverlay};
 use crate::{
-    debug,
-    level::Ground,
-    player::{movement::CharacterController, Player, PlayerStartupSet},
-    DEBUG,
+    level::{Ground, GroundMaterial, OneWayPlatform},
+    menu::AppState,
+    player::{movement::CharacterController, Player},
 };
 
 pub(super) struct MovementSubComponentsPlugin;
@@ -14,125 +15,160 @@ pub(super) struct MovementSubComponentsPlugin;
 impl Plugin for MovementSubComponentsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            PostStartup,
-            spawn_grounded_checkers.after(PlayerStartupSet::Movement),
-        )
-        .add_systems(
             PreUpdate,
-            (surface_checker, debug_surface_checker.run_if(debug)).chain(),
+            (activate_grounded_delay, surface_checker)
+                .chain()
+                .run_if(in_state(AppState::InGame)),
         )
         .add_event::<ActivateGroundedDelay>()
         .register_type::<Surface>()
+        .register_type::<SurfaceContact>()
         .register_type::<SurfaceGroundedChecker>();
+
+        #[cfg(feature = "debug")]
+        app.add_systems(
+            PreUpdate,
+            debug_surface_checker
+                .after(surface_checker)
+                .run_if(overlay_enabled(DebugOverlay::SurfaceSensors)),
+        );
     }
 }
 
-fn spawn_grounded_checkers(
-    mut cmd: Commands,
-    player_query: Query<(Entity, &CharacterController), With<Player>>,
+#[cfg(feature = "debug")]
+fn debug_surface_checker(
+    controller_query: Query<(&CharacterController, &GlobalTransform), With<Player>>,
+    mut gizmos: Gizmos,
 ) {
-    let (entity, controller) = player_query.single();
-    let size_div = 2f32;
-
-    let mut generate_child = |surface: Surface| -> Entity {
-        let size = match surface {
-            Surface::Top | Surface::Bottom => Vec2::new(controller.size.x / size_div, 1f32),
-            Surface::Left | Surface::Right => Vec2::new(1f32, controller.size.y / size_div),
-        };
-        let child = cmd
-            .spawn((
-                SpatialBundle {
-                    transform: Transform::from_translation(
-                        match surface {
-                            Surface::Top => Vec3::Y * controller.size.y / 2f32,
-                            Surface::Bottom => Vec3::NEG_Y * controller.size.y / 2f32,
-                            Surface::Right => Vec3::X * controller.size.x / 2f32,
-                            Surface::Left => Vec3::NEG_X * controller.size.x / 2f32,
-                        } + Vec3::Z * 5f32,
-                    ),
-                    ..Default::default()
-                },
-                Collider::cuboid(size.x / 2f32, size.y / 2f32),
-                Sensor,
-                SurfaceChecker(surface),
-                Name::from(format!("{surface:?} checker")),
-            ))
-            .id();
+    for (controller, transform) in controller_query.iter() {
+        let checker = &controller.surface_checker;
+        let center = transform.translation().truncate();
 
-        if DEBUG {
-            cmd.entity(child).insert((
-                Sprite {
-                    custom_size: Some(size * size_div * 0.9f32),
-                    color: Color::WHITE,
-                    ..Default::default()
-                },
-                DEFAULT_IMAGE_HANDLE.typed::<Image>(),
-            ));
-        }
-
-        child
-    };
+        for surface in Surface::ALL {
+            let (origin, shape) = checker.cast_shape(&surface, controller.size);
+            let cast_length = checker.cast_length();
+            let cast_area = shape + surface.direction().abs() * cast_length;
+            let cast_center = center + origin + surface.direction() * cast_length / 2f32;
 
-    let children = [
-        generate_child(Surface::Top),
-        generate_child(Surface::Bottom),
-        generate_child(Surface::Left),
-        generate_child(Surface::Right),
-    ];
+            let color = match (
+                checker.contacts.contains_key(&surface),
+                checker.surface_suppressed(&surface),
+            ) {
+                (_, true) => Color::YELLOW,
+                (true, false) => Color::LIME_GREEN,
+                (false, false) => Color::RED,
+            };
+            gizmos.rect_2d(cast_center, 0f32, cast_area, color);
 
-    cmd.entity(entity).push_children(&children);
+            if let Some(contact) = checker.contacts.get(&surface) {
+                let point = cast_center + surface.direction() * cast_length / 2f32;
+                gizmos.line_2d(point, point + contact.normal * 10f32, Color::CYAN);
+            }
+        }
+    }
 }
 
-#[derive(Component)]
-struct SurfaceChecker(Surface);
+/// How far inside the character's collider the surface casts start
+const CAST_SKIN: f32 = 0.5f32;
 
-fn debug_surface_checker(
-    controller_query: Query<&CharacterController, With<Player>>,
-    mut debug_query: Query<(&mut Sprite, &SurfaceChecker)>,
-) {
-    let controller = controller_query.single();
-
-    for (mut sprite, surface) in debug_query.iter_mut() {
-        let touching = *controller
-            .surface_checker
-            .touching_surfaces
-            .get(&surface.0)
-            .expect(&format!(
-                "Surface checker did not include surface {:?}",
-                surface.0
-            ));
-
-        sprite.color = match touching {
-            true => Color::LIME_GREEN,
-            false => Color::RED,
-        };
-    }
+/// What a surface of the character hit in the last shape cast
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct SurfaceContact {
+    pub entity: Entity,
+    /// World space normal of the hit ground, pointing towards the character
+    pub normal: Vec2,
+    /// Distance the cast travelled before hitting, zero when already overlapping
+    pub distance: f32,
+    pub material: GroundMaterial,
 }
 
 #[derive(Clone, Debug, Reflect)]
 pub struct SurfaceGroundedChecker {
-    touching_surfaces: HashMap<Surface, bool>,
+    contacts: HashMap<Surface, SurfaceContact>,
+    suppressed_surfaces: HashMap<Surface, Timer>,
+    pub grounded_delay: Duration,
+    /// How far past the character's collider each surface looks for ground
+    pub cast_distance: f32,
 }
 
 impl SurfaceGroundedChecker {
-    fn set_surface(&mut self, surface: &Surface, value: bool) {
-        self.touching_surfaces.insert(*surface, value);
+    pub fn new(grounded_delay: Duration) -> Self {
+        Self {
+            grounded_delay,
+            ..Default::default()
+        }
+    }
+
+    fn set_contact(&mut self, surface: &Surface, contact: Option<SurfaceContact>) {
+        match contact {
+            Some(contact) => self.contacts.insert(*surface, contact),
+            None => self.contacts.remove(surface),
+        };
+    }
+
+    /// Offset from the character's center the cast for `surface` starts at, and the size of the
+    /// cast shape. The shape starts just inside the collider, so it can't start past a wall
+    fn cast_shape(&self, surface: &Surface, size: Vec2) -> (Vec2, Vec2) {
+        let size_div = 2f32;
+
+        let shape = match surface {
+            Surface::Top | Surface::Bottom => Vec2::new(size.x / size_div, 1f32),
+            Surface::Left | Surface::Right => Vec2::new(1f32, size.y / size_div),
+        };
+        let half_extent = (surface.direction() * size / 2f32).length();
+
+        let thickness = (surface.direction() * shape / 2f32).length();
+
+        (
+            surface.direction() * (half_extent - CAST_SKIN - thickness),
+            shape,
+        )
+    }
+
+    /// Distance a cast travels, from its start inside the collider to `cast_distance` past it
+    fn cast_length(&self) -> f32 {
+        self.cast_distance + CAST_SKIN
+    }
+
+    /// Reports `surface` as not touching the ground for `duration`, whatever its sensor says
+    pub fn suppress_surface(&mut self, surface: &Surface, duration: Duration) {
+        self.suppressed_surfaces
+            .insert(*surface, Timer::new(duration, TimerMode::Once));
+    }
+
+    pub fn surface_suppressed(&self, surface: &Surface) -> bool {
+        self.suppressed_surfaces
+            .get(surface)
+            .is_some_and(|timer| !timer.finished())
+    }
+
+    fn tick_suppression(&mut self, delta: Duration) {
+        self.suppressed_surfaces.retain(|_, timer| {
+            timer.tick(delta);
+            !timer.finished()
+        });
     }
 
     pub fn surface_touching_ground(&self, surface: &Surface) -> bool {
-        *self.touching_surfaces.get(surface).unwrap()
+        self.contact(surface).is_some()
+    }
+
+    /// What `surface` is touching, or `None` if it isn't touching ground or is suppressed
+    pub fn contact(&self, surface: &Surface) -> Option<&SurfaceContact> {
+        self.contacts
+            .get(surface)
+            .filter(|_| !self.surface_suppressed(surface))
     }
 }
 
 impl Default for SurfaceGroundedChecker {
     fn default() -> Self {
-        let mut touching_surfaces = HashMap::new();
-        touching_surfaces.insert(Surface::Top, false);
-        touching_surfaces.insert(Surface::Bottom, false);
-        touching_surfaces.insert(Surface::Left, false);
-        touching_surfaces.insert(Surface::Right, false);
-
-        Self { touching_surfaces }
+        Self {
+            contacts: HashMap::new(),
+            suppressed_surfaces: HashMap::new(),
+            grounded_delay: Duration::from_secs_f32(0.1f32),
+            cast_distance: 2f32,
+        }
     }
 }
 
@@ -144,34 +180,232 @@ pub enum Surface {
     Right,
 }
 
+impl Surface {
+    pub const ALL: [Surface; 4] = [Surface::Top, Surface::Bottom, Surface::Left, Surface::Right];
+
+    /// Direction from the center of the character towards the surface
+    pub fn direction(&self) -> Vec2 {
+        match self {
+            Surface::Top => Vec2::Y,
+            Surface::Bottom => Vec2::NEG_Y,
+            Surface::Left => Vec2::NEG_X,
+            Surface::Right => Vec2::X,
+        }
+    }
+}
+
 fn surface_checker(
-    mut player_query: Query<(Entity, &mut CharacterController), With<Player>>,
-    checker_query: Query<(&Collider, &GlobalTransform, &SurfaceChecker)>,
-    ground_query: Query<Entity, (With<Ground>, With<Collider>)>,
+    mut player_query: Query<(Entity, &GlobalTransform, &mut CharacterController)>,
+    ground_query: Query<Option<&GroundMaterial>, With<Ground>>,
+    one_way_query: Query<(), With<OneWayPlatform>>,
     ctx: Res<RapierContext>,
 ) {
-    let (player, mut controller) = player_query.single_mut();
-
     let ground_query_predicate = |e| ground_query.contains(e);
+    // One-way platforms are only stood on, never hit from the side or below
+    let solid_query_predicate = |e| ground_query.contains(e) && !one_way_query.contains(e);
 
-    let filter = QueryFilter::new()
-        .exclude_sensors()
-        .exclude_rigid_body(player)
-        .predicate(&ground_query_predicate);
-
-    for (col, pos, surface) in checker_query
-        .iter()
-        .map(|(c, t, s)| (c, t.translation().truncate(), s.0))
-        .collect::<Vec<(&Collider, Vec2, Surface)>>()
-        .iter()
-    {
-        controller.surface_checker.set_surface(
-            surface,
-            ctx.intersection_with_shape(*pos, 0f32, col, filter)
-                .is_some(),
-        );
+    for (player, transform, mut controller) in player_query.iter_mut() {
+        let center = transform.translation().truncate();
+        let size = controller.size;
+
+        for surface in Surface::ALL {
+            let filter = QueryFilter::new()
+                .exclude_sensors()
+                .exclude_rigid_body(player);
+            let filter = if surface == Surface::Bottom {
+                filter.predicate(&ground_query_predicate)
+            } else {
+                filter.predicate(&solid_query_predicate)
+            };
+
+            let checker = &controller.surface_checker;
+            let (origin, shape) = checker.cast_shape(&surface, size);
+            let cast_length = checker.cast_length();
+
+            let contact = ctx
+                .cast_shape(
+                    center + origin,
+                    0f32,
+                    surface.direction() * cast_length,
+                    &Collider::cuboid(shape.x / 2f32, shape.y / 2f32),
+                    1f32,
+                    filter,
+                )
+                // Characters passing through a one-way platform aren't standing on it
+                .filter(|(entity, toi)| {
+                    toi.status != TOIStatus::Penetrating || !one_way_query.contains(*entity)
+                })
+                .map(|(entity, toi)| {
+                    let material = ground_query.get(entity).ok().flatten();
+                    let normal = match toi.status {
+                        TOIStatus::Penetrating => -surface.direction(),
+                        _ => -toi.normal1,
+                    };
+
+                    SurfaceContact {
+                        entity,
+                        normal,
+                        distance: (toi.toi * cast_length - CAST_SKIN).max(0f32),
+                        material: material.copied().unwrap_or_default(),
+                    }
+                });
+
+            controller.surface_checker.set_contact(&surface, contact);
+        }
     }
 }
 
+/// Stops a surface of the player from counting as grounded for the controller's `grounded_delay`,
+/// so the sensor can't re-ground the player while it is still leaving the ground
 #[derive(Event, Clone, Copy, PartialEq, Eq, Reflect)]
 pub struct ActivateGroundedDelay(pub Surface);
+
+fn activate_grounded_delay(
+    mut player_query: Query<&mut CharacterController, With<Player>>,
+    mut events: EventReader<ActivateGroundedDelay>,
+    time: Res<Time>,
+) {
+    let Ok(mut controller) = player_query.get_single_mut() else {
+        return;
+    };
+
+    controller.surface_checker.tick_suppression(time.delta());
+    for ActivateGroundedDelay(surface) in events.iter() {
+        let delay = controller.surface_checker.grounded_delay;
+        controller.surface_checker.suppress_surface(surface, delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        player::{
+            movement::{jump, CharacterControllerBuilder},
+            state_machine::{definition::*, states::*},
+        },
+        GRAVITY,
+    };
+    use bevy::time::TimeUpdateStrategy;
+    use seldom_state::prelude::*;
+
+    const STEP: f32 = 1f32 / 60f32;
+
+    fn contact() -> SurfaceContact {
+        SurfaceContact {
+            entity: Entity::from_raw(0),
+            normal: Vec2::Y,
+            distance: 0f32,
+            material: GroundMaterial::NORMAL,
+        }
+    }
+
+    #[test]
+    fn suppressed_surface_is_not_touching_ground_until_timer_expires() {
+        let delay = Duration::from_millis(100);
+        let mut checker = SurfaceGroundedChecker::new(delay);
+        checker.set_contact(&Surface::Bottom, Some(contact()));
+        checker.set_contact(&Surface::Left, Some(contact()));
+        assert!(checker.surface_touching_ground(&Surface::Bottom));
+
+        checker.suppress_surface(&Surface::Bottom, delay);
+        assert!(!checker.surface_touching_ground(&Surface::Bottom));
+        assert!(checker.contact(&Surface::Bottom).is_none());
+        // Other surfaces keep reporting their contacts
+        assert!(checker.surface_touching_ground(&Surface::Left));
+
+        checker.tick_suppression(delay / 2);
+        assert!(!checker.surface_touching_ground(&Surface::Bottom));
+
+        checker.tick_suppression(delay / 2);
+        assert!(checker.surface_touching_ground(&Surface::Bottom));
+        assert!(!checker.surface_suppressed(&Surface::Bottom));
+    }
+
+    /// A player standing on a floor with its top at y = 0, about to jump, and a bottom cast long
+    /// enough to keep touching the floor for the whole grounded delay
+    fn jumping_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100f32),
+            StateMachinePlugin,
+            MovementSubComponentsPlugin,
+        ))
+        .add_state::<AppState>()
+        .insert_resource(RapierConfiguration {
+            gravity: Vec2::new(0f32, -GRAVITY),
+            timestep_mode: TimestepMode::Fixed {
+                dt: STEP,
+                substeps: 1,
+            },
+            ..Default::default()
+        })
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            STEP,
+        )))
+        .add_systems(Update, jump);
+        #[cfg(feature = "debug")]
+        app.init_resource::<crate::debug::DebugSettings>();
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InGame);
+
+        app.world.spawn((
+            TransformBundle::from_transform(Transform::from_xyz(0f32, -25f32, 0f32)),
+            RigidBody::Fixed,
+            Collider::cuboid(200f32, 25f32),
+            Ground,
+        ));
+
+        // A new controller's jump buffer and coyote timers haven't run out, so it jumps right away
+        let controller = CharacterControllerBuilder {
+            cast_distance: 60f32,
+            ..Default::default()
+        }
+        .build();
+        let definition =
+            StateMachineDefinition::parse(DEFAULT_PLAYER_DEFINITION, &TriggerRegistry::default())
+                .unwrap();
+        let player = app
+            .world
+            .spawn((
+                TransformBundle::from_transform(Transform::from_xyz(0f32, 25f32, 0f32)),
+                RigidBody::Dynamic,
+                controller.collider(),
+                GravityScale(controller.gravity_scale),
+                Velocity::default(),
+                LockedAxes::ROTATION_LOCKED,
+                controller,
+                Player,
+                GroundedState::Idle,
+                definition.machine().build().0,
+            ))
+            .id();
+
+        (app, player)
+    }
+
+    #[test]
+    fn bottom_sensor_does_not_cancel_a_jump() {
+        let (mut app, player) = jumping_app();
+        let took_off = (0..10).any(|_| {
+            app.update();
+            app.world.get::<FallingState>(player).is_some()
+        });
+        assert!(took_off, "the player never jumped");
+
+        let delay = CharacterControllerBuilder::default().grounded_delay;
+        for _ in 0..(delay / STEP) as usize {
+            app.update();
+
+            let controller = app.world.get::<CharacterController>(player).unwrap();
+            assert!(controller
+                .surface_checker
+                .contacts
+                .contains_key(&Surface::Bottom));
+            assert!(app.world.get::<GroundedState>(player).is_none());
+        }
+    }
+}
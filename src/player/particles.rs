@@ -0,0 +1,267 @@
+use super::{
+    movement::{sub_components::Surface, CharacterController},
+    state_machine::states::{FallingState, GroundedState, JumpingState},
+    Player, PlayerSet, PlayerStartupSet,
+};
+use crate::particles::{ColorGradient, EmitterDef, SpawnParticles};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+pub(super) struct PlayerParticlesPlugin;
+
+impl Plugin for PlayerParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, init.in_set(PlayerStartupSet::Visuals))
+            .add_systems(
+                Update,
+                (running_dust, jump_puff, landing_ring, wall_slide_sparks)
+                    .in_set(PlayerSet::Visuals),
+            );
+    }
+}
+
+fn init(mut cmd: Commands, player_query: Query<Entity, With<Player>>) {
+    cmd.entity(player_query.single())
+        .insert(PlayerParticles::default());
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct PlayerParticles {
+    pub run_dust: EmitterDef,
+    pub min_dust_speed: f32,
+    pub jump_puff: EmitterDef,
+    pub land_ring: EmitterDef,
+    pub wall_sparks: EmitterDef,
+
+    pub dust_accumulator: f32,
+    pub spark_accumulator: f32,
+    pub was_grounded: bool,
+}
+
+impl Default for PlayerParticles {
+    fn default() -> Self {
+        let dust = ColorGradient::new(vec![
+            (0f32, Color::rgba_u8(230, 220, 200, 200)),
+            (1f32, Color::rgba_u8(230, 220, 200, 0)),
+        ]);
+
+        Self {
+            run_dust: EmitterDef {
+                rate: 30f32,
+                lifetime: 0.35f32,
+                lifetime_variance: 0.1f32,
+                size: 4f32,
+                gradient: dust.clone(),
+                min_speed: 20f32,
+                max_speed: 60f32,
+                spread: FRAC_PI_2,
+                gravity: 50f32,
+                drag: 4f32,
+                ..Default::default()
+            },
+            min_dust_speed: 100f32,
+            jump_puff: EmitterDef {
+                burst: 8,
+                lifetime: 0.3f32,
+                lifetime_variance: 0.05f32,
+                size: 5f32,
+                gradient: dust.clone(),
+                min_speed: 60f32,
+                max_speed: 120f32,
+                spread: PI,
+                even_spread: true,
+                drag: 6f32,
+                ..Default::default()
+            },
+            land_ring: EmitterDef {
+                burst: 16,
+                lifetime: 0.4f32,
+                size: 4f32,
+                gradient: dust,
+                min_speed: 120f32,
+                max_speed: 120f32,
+                spread: TAU,
+                even_spread: true,
+                drag: 5f32,
+                ..Default::default()
+            },
+            wall_sparks: EmitterDef {
+                rate: 40f32,
+                lifetime: 0.25f32,
+                lifetime_variance: 0.1f32,
+                size: 3f32,
+                gradient: ColorGradient::new(vec![
+                    (0f32, Color::rgb_u8(255, 240, 150)),
+                    (0.5f32, Color::rgb_u8(255, 140, 40)),
+                    (1f32, Color::rgba_u8(255, 60, 0, 0)),
+                ]),
+                min_speed: 80f32,
+                max_speed: 160f32,
+                spread: FRAC_PI_2,
+                gravity: -400f32,
+                ..Default::default()
+            },
+
+            dust_accumulator: 0f32,
+            spark_accumulator: 0f32,
+            was_grounded: false,
+        }
+    }
+}
+
+/// Turns a per-second rate into a whole number of particles for this frame
+fn take_from_rate(accumulator: &mut f32, rate: f32, dt: f32) -> usize {
+    *accumulator += rate * dt;
+    let count = accumulator.floor();
+    *accumulator -= count;
+    count as usize
+}
+
+fn running_dust(
+    mut player_query: Query<
+        (
+            &mut PlayerParticles,
+            &CharacterController,
+            &Velocity,
+            &GlobalTransform,
+        ),
+        With<GroundedState>,
+    >,
+    mut particles: EventWriter<SpawnParticles>,
+    time: Res<Time>,
+) {
+    for (mut effects, controller, vel, transform) in player_query.iter_mut() {
+        if vel.linvel.x.abs() < effects.min_dust_speed
+            || !controller
+                .surface_checker
+                .surface_touching_ground(&Surface::Bottom)
+        {
+            effects.dust_accumulator = 0f32;
+            continue;
+        }
+
+        let rate = effects.run_dust.rate;
+        let count = take_from_rate(&mut effects.dust_accumulator, rate, time.delta_seconds());
+        if count == 0 {
+            continue;
+        }
+
+        // Kick the dust up behind the feet
+        let direction = Vec2::new(-vel.linvel.x.signum(), 1f32).normalize();
+        particles.send(SpawnParticles {
+            emitter: effects.run_dust.clone(),
+            position: transform.translation().truncate()
+                + Surface::Bottom.direction() * controller.size.y / 2f32,
+            direction,
+            count,
+        });
+    }
+}
+
+fn jump_puff(
+    player_query: Query<
+        (&PlayerParticles, &CharacterController, &GlobalTransform),
+        Added<JumpingState>,
+    >,
+    mut particles: EventWriter<SpawnParticles>,
+) {
+    for (effects, controller, transform) in player_query.iter() {
+        particles.send(SpawnParticles::burst(
+            &effects.jump_puff,
+            transform.translation().truncate()
+                + Surface::Bottom.direction() * controller.size.y / 2f32,
+            Vec2::Y,
+        ));
+    }
+}
+
+fn landing_ring(
+    mut player_query: Query<(&mut PlayerParticles, &CharacterController, &GlobalTransform)>,
+    mut particles: EventWriter<SpawnParticles>,
+) {
+    for (mut effects, controller, transform) in player_query.iter_mut() {
+        let grounded = controller
+            .surface_checker
+            .surface_touching_ground(&Surface::Bottom);
+
+        if grounded && !effects.was_grounded {
+            particles.send(SpawnParticles::burst(
+                &effects.land_ring,
+                transform.translation().truncate()
+                    + Surface::Bottom.direction() * controller.size.y / 2f32,
+                Vec2::Y,
+            ));
+        }
+        effects.was_grounded = grounded;
+    }
+}
+
+fn wall_slide_sparks(
+    mut player_query: Query<
+        (
+            &mut PlayerParticles,
+            &CharacterController,
+            &Velocity,
+            &GlobalTransform,
+        ),
+        With<FallingState>,
+    >,
+    mut particles: EventWriter<SpawnParticles>,
+    time: Res<Time>,
+) {
+    for (mut effects, controller, vel, transform) in player_query.iter_mut() {
+        let checker = &controller.surface_checker;
+        let wall = [Surface::Left, Surface::Right]
+            .into_iter()
+            .find(|surface| checker.surface_touching_ground(surface));
+
+        let Some(wall) = wall.filter(|_| vel.linvel.y < 0f32) else {
+            effects.spark_accumulator = 0f32;
+            continue;
+        };
+
+        let rate = effects.wall_sparks.rate;
+        let count = take_from_rate(&mut effects.spark_accumulator, rate, time.delta_seconds());
+        if count == 0 {
+            continue;
+        }
+
+        // Sparks fly off the wall and slightly upwards, against the slide
+        let direction = (-wall.direction() + Vec2::Y * 0.5f32).normalize();
+        particles.send(SpawnParticles {
+            emitter: effects.wall_sparks.clone(),
+            position: transform.translation().truncate()
+                + wall.direction() * controller.size.x / 2f32,
+            direction,
+            count,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fractions_of_a_particle_carry_over() {
+        let mut accumulator = 0f32;
+        let counts = (0..6)
+            .map(|_| take_from_rate(&mut accumulator, 25f32, 0.1f32))
+            .collect::<Vec<_>>();
+
+        // 2.5 particles a frame
+        assert_eq!(counts, vec![2, 3, 2, 3, 2, 3]);
+        assert!(accumulator < 1f32);
+    }
+
+    #[test]
+    fn slow_rates_still_emit() {
+        let mut accumulator = 0f32;
+        let total = (0..60)
+            .map(|_| take_from_rate(&mut accumulator, 2f32, 1f32 / 60f32))
+            .sum::<usize>();
+
+        assert_eq!(total, 2);
+    }
+}
@@ -1,45 +1,94 @@
-use super::{input::InputAction, Player, PlayerStartupSet};
+use super::{facing::Facing, input::InputAction, Player, PlayerStartupSet};
 use bevy::prelude::*;
 
+pub mod definition;
+pub mod graph;
+pub mod history;
 pub mod triggers;
+use definition::*;
+use graph::*;
+use history::*;
 use seldom_state::prelude::*;
 use states::*;
-use triggers::*;
+use triggers::{BounceTrigger, DeadTrigger};
 
 pub(super) struct PlayerStateMachinePlugin;
 
 impl Plugin for PlayerStateMachinePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, init.in_set(PlayerStartupSet::StateMachine));
+        app.add_plugins(StateHistoryPlugin)
+            .init_resource::<TriggerRegistry>()
+            .add_systems(Startup, init.in_set(PlayerStartupSet::StateMachine));
     }
 }
 
-pub fn init(mut cmd: Commands, player_query: Query<Entity, With<Player>>) {
-    cmd.entity(player_query.single()).insert((
-        GroundedState::Idle,
-        StateMachine::default()
-            .trans::<JumpingState>(AlwaysTrigger, FallingState)
-            .trans::<FallingState>(GroundedTrigger, GroundedState::Idle)
-            .trans::<FallingState>(JumpTrigger, JumpingState(1f32))
-            .trans::<GroundedState>(JumpTrigger, JumpingState(1f32))
-            .trans::<GroundedState>(GroundedTrigger.not().and(FallingTrigger), FallingState)
-            .trans_builder(
-                ValueTrigger::unbounded(InputAction::Run),
-                |_: &GroundedState, value| {
-                    Some(match value {
-                        value if value > 0.5f32 => GroundedState::WalkingRight,
-                        value if value < 0.5f32 => GroundedState::WalkingLeft,
-                        _ => GroundedState::Idle,
-                    })
-                },
-            ),
-    ));
+pub fn init(
+    mut cmd: Commands,
+    player_query: Query<Entity, With<Player>>,
+    registry: Res<TriggerRegistry>,
+) {
+    let definition = load_definition(PLAYER_DEFINITION_PATH, &registry);
+    let (machine, _) = player_state_machine(&definition).build();
+
+    let mut player = cmd.entity(player_query.single());
+    definition.initial.insert_initial(&mut player);
+    player.insert((StateHistory::default(), machine));
+}
+
+/// Loads a definition file, falling back to the built in player definition if it is invalid
+pub fn load_definition(path: &str, registry: &TriggerRegistry) -> StateMachineDefinition {
+    StateMachineDefinition::load(path, registry).unwrap_or_else(|errors| {
+        for error in errors {
+            println!("Invalid state machine definition {path}, {error}");
+        }
+        StateMachineDefinition::parse(DEFAULT_PLAYER_DEFINITION, registry)
+            .expect("Built in player state machine definition is invalid")
+    })
+}
+
+/// Builds the transitions of `definition`, plus dying, bouncing and walking. Dying comes first so
+/// it wins over every other transition, and the rest pass values on to their states, so none of
+/// them can be expressed in a definition file
+pub fn player_state_machine(definition: &StateMachineDefinition) -> GraphedStateMachine {
+    let machine = definition
+        .initial
+        .machine()
+        .trans::<AnyState>(DeadTrigger, DeadState)
+        .trans::<DeadState>(DoneTrigger::Success, FallingState);
+
+    definition
+        .add_transitions(machine)
+        .trans_builder(BounceTrigger, |_: &GroundedState, multi| {
+            Some(JumpingState(multi))
+        })
+        .trans_builder(
+            ValueTrigger::unbounded(InputAction::Run),
+            |_: &GroundedState, value| {
+                Some(match Facing::from_value(value, 0.5f32) {
+                    Some(Facing::Right) => GroundedState::WalkingRight,
+                    Some(Facing::Left) => GroundedState::WalkingLeft,
+                    None => GroundedState::Idle,
+                })
+            },
+        )
+}
+
+/// Writes the player's transition graph to `path` in Graphviz DOT format
+pub fn export_state_graph(path: &std::path::Path) -> std::io::Result<()> {
+    let definition = load_definition(PLAYER_DEFINITION_PATH, &TriggerRegistry::default());
+    let (_, graph) = player_state_machine(&definition).build();
+
+    for state in graph.unreachable_states() {
+        println!("Warning: player state {state} can not be reached");
+    }
+
+    std::fs::write(path, graph.to_dot("PlayerStateMachine"))
 }
 
 pub mod states {
     use bevy::prelude::*;
 
-    #[derive(Clone, Copy, Component, Reflect)]
+    #[derive(Clone, Copy, Debug, PartialEq, Component, Reflect)]
     #[component(storage = "SparseSet")]
     pub enum GroundedState {
         WalkingLeft = -1,
@@ -47,11 +96,17 @@ pub mod states {
         WalkingRight = 1,
     }
 
-    #[derive(Clone, Copy, Component, Reflect)]
+    #[derive(Clone, Copy, Debug, PartialEq, Component, Reflect)]
     #[component(storage = "SparseSet")]
     pub struct JumpingState(pub f32);
 
-    #[derive(Clone, Copy, Component, Reflect)]
+    #[derive(Clone, Copy, Debug, PartialEq, Component, Reflect)]
     #[component(storage = "SparseSet")]
     pub struct FallingState;
+
+    /// Entered from any state when the player's health runs out. The state is left once the death
+    /// has played out and the player has respawned
+    #[derive(Clone, Copy, Debug, PartialEq, Component, Reflect)]
+    #[component(storage = "SparseSet")]
+    pub struct DeadState;
 }
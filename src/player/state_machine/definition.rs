@@ -0,0 +1,560 @@
+use bevy::prelude::*;
+use seldom_state::prelude::*;
+use std::{any::type_name, collections::HashMap, fmt, path::Path, sync::Arc};
+
+use super::{graph::GraphedStateMachine, states::*, triggers::*};
+use crate::{level::resolve_path, player::movement::CharacterController};
+
+/// Definition the player falls back to when its definition file can't be loaded
+pub const DEFAULT_PLAYER_DEFINITION: &str =
+    include_str!("../../../assets/state_machines/player.states");
+/// Relative to the folder the assets folder is in, like level paths
+pub const PLAYER_DEFINITION_PATH: &str = "assets/state_machines/player.states";
+
+/// Names of the components in [`states`](super::states) that can be used in definition files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StateName {
+    Grounded,
+    Jumping,
+    Falling,
+    Dead,
+}
+
+impl StateName {
+    pub const ALL: [StateName; 4] = [
+        StateName::Grounded,
+        StateName::Jumping,
+        StateName::Falling,
+        StateName::Dead,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StateName::Grounded => "grounded",
+            StateName::Jumping => "jumping",
+            StateName::Falling => "falling",
+            StateName::Dead => "dead",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|state| state.name() == name)
+    }
+
+    pub fn insert_initial(&self, entity: &mut bevy::ecs::system::EntityCommands) {
+        match self {
+            StateName::Grounded => entity.insert(GroundedState::Idle),
+            StateName::Jumping => entity.insert(JumpingState(1f32)),
+            StateName::Falling => entity.insert(FallingState),
+            StateName::Dead => entity.insert(DeadState),
+        };
+    }
+
+    pub fn machine(&self) -> GraphedStateMachine {
+        match self {
+            StateName::Grounded => GraphedStateMachine::new::<GroundedState>(),
+            StateName::Jumping => GraphedStateMachine::new::<JumpingState>(),
+            StateName::Falling => GraphedStateMachine::new::<FallingState>(),
+            StateName::Dead => GraphedStateMachine::new::<DeadState>(),
+        }
+    }
+
+    fn add_transition(
+        &self,
+        machine: GraphedStateMachine,
+        trigger: TriggerExpr,
+        to: StateName,
+    ) -> GraphedStateMachine {
+        match self {
+            StateName::Grounded => to.add_transition_from::<GroundedState>(machine, trigger),
+            StateName::Jumping => to.add_transition_from::<JumpingState>(machine, trigger),
+            StateName::Falling => to.add_transition_from::<FallingState>(machine, trigger),
+            StateName::Dead => to.add_transition_from::<DeadState>(machine, trigger),
+        }
+    }
+
+    fn add_transition_from<S: MachineState>(
+        &self,
+        machine: GraphedStateMachine,
+        trigger: TriggerExpr,
+    ) -> GraphedStateMachine {
+        let label = trigger.to_string();
+        match self {
+            StateName::Grounded => machine.trans_labeled::<S>(trigger, label, GroundedState::Idle),
+            StateName::Jumping => machine.trans_labeled::<S>(trigger, label, JumpingState(1f32)),
+            StateName::Falling => machine.trans_labeled::<S>(trigger, label, FallingState),
+            StateName::Dead => machine.trans_labeled::<S>(trigger, label, DeadState),
+        }
+    }
+}
+
+/// Trigger built from a definition file, combining registered [`ControllerTrigger`]s
+#[derive(Clone)]
+pub enum TriggerExpr {
+    Always,
+    Controller {
+        type_name: String,
+        trigger: Arc<dyn ControllerTrigger>,
+    },
+    Not(Box<TriggerExpr>),
+    And(Box<TriggerExpr>, Box<TriggerExpr>),
+    Or(Box<TriggerExpr>, Box<TriggerExpr>),
+}
+
+impl TriggerExpr {
+    pub fn evaluate(&self, controller: &CharacterController) -> bool {
+        match self {
+            TriggerExpr::Always => true,
+            TriggerExpr::Controller { trigger, .. } => trigger.check(controller),
+            TriggerExpr::Not(expr) => !expr.evaluate(controller),
+            TriggerExpr::And(a, b) => a.evaluate(controller) && b.evaluate(controller),
+            TriggerExpr::Or(a, b) => a.evaluate(controller) || b.evaluate(controller),
+        }
+    }
+}
+
+/// Formats the same way the trigger types would be labeled in the state graph
+impl fmt::Display for TriggerExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerExpr::Always => write!(f, "AlwaysTrigger"),
+            TriggerExpr::Controller { type_name, .. } => write!(f, "{type_name}"),
+            TriggerExpr::Not(expr) => write!(f, "NotTrigger<{expr}>"),
+            TriggerExpr::And(a, b) => write!(f, "AndTrigger<{a}, {b}>"),
+            TriggerExpr::Or(a, b) => write!(f, "OrTrigger<{a}, {b}>"),
+        }
+    }
+}
+
+impl fmt::Debug for TriggerExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl BoolTrigger for TriggerExpr {
+    type Param<'w, 's> = Query<'w, 's, &'static CharacterController>;
+
+    fn trigger(&self, entity: Entity, param: Self::Param<'_, '_>) -> bool {
+        match param.get(entity) {
+            Ok(controller) => self.evaluate(controller),
+            Err(message) => {
+                println!("Could not get character controller in {self}. Error message: {message}");
+                false
+            }
+        }
+    }
+}
+
+/// Maps the trigger names used in definition files to trigger types. `not`, `and` and `or` are
+/// reserved for combining triggers
+#[derive(Resource, Clone)]
+pub struct TriggerRegistry {
+    triggers: HashMap<String, TriggerExpr>,
+}
+
+impl TriggerRegistry {
+    pub const COMBINATORS: [&'static str; 3] = ["not", "and", "or"];
+
+    pub fn empty() -> Self {
+        Self {
+            triggers: HashMap::new(),
+        }
+    }
+
+    pub fn register<T: ControllerTrigger>(&mut self, name: &str, trigger: T) -> &mut Self {
+        assert!(
+            !Self::COMBINATORS.contains(&name),
+            "{name} is reserved for combining triggers"
+        );
+
+        let full_name = type_name::<T>();
+        let type_name = full_name.rsplit("::").next().unwrap_or(full_name);
+        self.triggers.insert(
+            name.to_owned(),
+            TriggerExpr::Controller {
+                type_name: type_name.to_owned(),
+                trigger: Arc::new(trigger),
+            },
+        );
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<TriggerExpr> {
+        self.triggers.get(name).cloned()
+    }
+}
+
+impl Default for TriggerRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .register("jump", JumpTrigger)
+            .register("grounded", GroundedTrigger)
+            .register("falling", FallingTrigger)
+            .register("wallsliding", WallslidingTrigger)
+            .triggers
+            .insert("always".to_owned(), TriggerExpr::Always);
+        registry
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefinitionError {
+    /// 1-based line number, if the error belongs to a single line
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TransitionDefinition {
+    pub line: usize,
+    pub from: StateName,
+    pub trigger: TriggerExpr,
+    pub to: StateName,
+}
+
+/// States and transitions of a state machine, read from a definition file. Every line is either
+/// `initial <state>` or `<state> -> <state> when <trigger>`, and `#` starts a comment. Transitions
+/// are checked in the order they are written
+#[derive(Clone, Debug)]
+pub struct StateMachineDefinition {
+    pub initial: StateName,
+    pub transitions: Vec<TransitionDefinition>,
+}
+
+impl StateMachineDefinition {
+    pub fn load(
+        path: impl AsRef<Path>,
+        registry: &TriggerRegistry,
+    ) -> Result<Self, Vec<DefinitionError>> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(resolve_path(path)).map_err(|err| {
+            vec![DefinitionError {
+                line: None,
+                message: format!("could not read {}: {err}", path.display()),
+            }]
+        })?;
+
+        Self::parse(&source, registry)
+    }
+
+    pub fn parse(source: &str, registry: &TriggerRegistry) -> Result<Self, Vec<DefinitionError>> {
+        let mut errors = Vec::new();
+        let mut initial = None;
+        let mut transitions = Vec::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut error = |message: String| {
+                errors.push(DefinitionError {
+                    line: Some(line_number),
+                    message,
+                })
+            };
+
+            if let Some(state) = line.strip_prefix("initial ") {
+                match (parse_state(state.trim()), initial) {
+                    (_, Some(_)) => error("initial state is already set".to_owned()),
+                    (Ok(state), None) => initial = Some(state),
+                    (Err(message), None) => error(message),
+                }
+                continue;
+            }
+
+            let Some((from, rest)) = line.split_once("->") else {
+                error(format!(
+                    "expected `initial <state>` or `<state> -> <state> when <trigger>`, found `{line}`"
+                ));
+                continue;
+            };
+            let Some((to, trigger)) = rest.split_once(" when ") else {
+                error(format!("missing `when <trigger>` after `{}`", rest.trim()));
+                continue;
+            };
+
+            match (
+                parse_state(from.trim()),
+                parse_state(to.trim()),
+                parse_trigger(trigger.trim(), registry),
+            ) {
+                (Ok(from), Ok(to), Ok(trigger)) => transitions.push(TransitionDefinition {
+                    line: line_number,
+                    from,
+                    trigger,
+                    to,
+                }),
+                (from, to, trigger) => {
+                    for message in [from.err(), to.err(), trigger.err()].into_iter().flatten() {
+                        error(message);
+                    }
+                }
+            }
+        }
+
+        let Some(initial) = initial else {
+            errors.push(DefinitionError {
+                line: None,
+                message: "missing `initial <state>` line".to_owned(),
+            });
+            return Err(errors);
+        };
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Self {
+            initial,
+            transitions,
+        })
+    }
+
+    pub fn machine(&self) -> GraphedStateMachine {
+        self.add_transitions(self.initial.machine())
+    }
+
+    /// Adds the definition's transitions after the ones already in `machine`
+    pub fn add_transitions(&self, machine: GraphedStateMachine) -> GraphedStateMachine {
+        self.transitions
+            .iter()
+            .fold(machine, |machine, transition| {
+                transition
+                    .from
+                    .add_transition(machine, transition.trigger.clone(), transition.to)
+            })
+    }
+}
+
+fn parse_state(name: &str) -> Result<StateName, String> {
+    StateName::from_name(name).ok_or_else(|| {
+        let known = StateName::ALL.map(|state| state.name()).join(", ");
+        format!("unknown state `{name}`, expected one of: {known}")
+    })
+}
+
+fn parse_trigger(source: &str, registry: &TriggerRegistry) -> Result<TriggerExpr, String> {
+    let tokens = tokenize(source)?;
+    let mut parser = TriggerParser {
+        tokens: &tokens,
+        position: 0,
+        registry,
+    };
+
+    let expr = parser.expr()?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected `{token}` after trigger")),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | ')' | ',' => tokens.push(c.to_string()),
+            c if c.is_whitespace() => {}
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(ident);
+            }
+            c => return Err(format!("unexpected character `{c}` in trigger")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct TriggerParser<'a> {
+    tokens: &'a [String],
+    position: usize,
+    registry: &'a TriggerRegistry,
+}
+
+impl TriggerParser<'_> {
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected `{expected}`, found `{token}`")),
+            None => Err(format!("expected `{expected}`, found end of line")),
+        }
+    }
+
+    fn expr(&mut self) -> Result<TriggerExpr, String> {
+        let Some(name) = self.next().map(str::to_owned) else {
+            return Err("expected a trigger, found end of line".to_owned());
+        };
+
+        match name.as_str() {
+            "not" => {
+                self.expect("(")?;
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(TriggerExpr::Not(Box::new(expr)))
+            }
+            "and" | "or" => {
+                self.expect("(")?;
+                let mut expr = self.expr()?;
+                let mut count = 1;
+                while self.tokens.get(self.position).map(String::as_str) == Some(",") {
+                    self.position += 1;
+                    let other = Box::new(self.expr()?);
+                    expr = match name.as_str() {
+                        "and" => TriggerExpr::And(Box::new(expr), other),
+                        _ => TriggerExpr::Or(Box::new(expr), other),
+                    };
+                    count += 1;
+                }
+                self.expect(")")?;
+
+                if count < 2 {
+                    return Err(format!("`{name}` needs at least two triggers"));
+                }
+                Ok(expr)
+            }
+            "(" | ")" | "," => Err(format!("expected a trigger, found `{name}`")),
+            _ => self.registry.get(&name).ok_or_else(|| {
+                let mut known = self.registry.triggers.keys().cloned().collect::<Vec<_>>();
+                known.sort();
+                format!(
+                    "unknown trigger `{name}`, expected one of: {}, or {}",
+                    known.join(", "),
+                    TriggerRegistry::COMBINATORS.join(", ")
+                )
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<StateMachineDefinition, Vec<DefinitionError>> {
+        StateMachineDefinition::parse(source, &TriggerRegistry::default())
+    }
+
+    /// Lines of the errors in `source`, checking every error names its line
+    fn error_lines(source: &str) -> Vec<usize> {
+        parse(source)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.line.expect("error without a line"))
+            .collect()
+    }
+
+    #[test]
+    fn default_definition_is_valid() {
+        let definition = parse(DEFAULT_PLAYER_DEFINITION).unwrap();
+
+        assert_eq!(definition.initial, StateName::Grounded);
+        assert_eq!(definition.transitions.len(), 5);
+    }
+
+    #[test]
+    fn unknown_state_is_reported() {
+        let errors = parse("initial grounded\ngrounded -> flying when jump").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, Some(2));
+        assert!(errors[0].message.contains("unknown state `flying`"));
+    }
+
+    #[test]
+    fn unknown_trigger_is_reported() {
+        let errors = parse("initial grounded\ngrounded -> jumping when dash").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, Some(2));
+        assert!(errors[0].message.contains("unknown trigger `dash`"));
+    }
+
+    #[test]
+    fn nested_triggers_are_combined() {
+        let definition = parse(
+            "initial grounded\ngrounded -> falling when and(not(grounded), or(jump, falling))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            definition.transitions[0].trigger.to_string(),
+            "AndTrigger<NotTrigger<GroundedTrigger>, OrTrigger<JumpTrigger, FallingTrigger>>"
+        );
+    }
+
+    #[test]
+    fn combinators_are_checked() {
+        let source = "initial grounded
+grounded -> falling when and(grounded)
+grounded -> falling when not(grounded
+grounded -> falling when or(jump, falling) jump";
+
+        assert_eq!(error_lines(source), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn missing_initial_is_reported() {
+        let errors = parse("grounded -> jumping when jump").unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![DefinitionError {
+                line: None,
+                message: "missing `initial <state>` line".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn lines_count_comments_and_blank_lines() {
+        let source = "# A comment
+
+initial grounded # and another
+
+grounded -> jumping when jump
+# jumping -> flying when jump
+jumping -> flying when jump
+falling -> grounded";
+
+        assert_eq!(error_lines(source), vec![7, 8]);
+
+        let definition = parse(
+            &source
+                .replace("flying", "falling")
+                .replace("falling -> grounded", "falling -> grounded when grounded"),
+        )
+        .unwrap();
+        let lines = definition
+            .transitions
+            .iter()
+            .map(|transition| transition.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![5, 7, 8]);
+    }
+}
@@ -0,0 +1,263 @@
+use bevy::prelude::*;
+use seldom_state::prelude::*;
+use std::{any::type_name, collections::HashSet, fmt::Write};
+
+/// Wraps a [`StateMachine`] while it is configured and records every transition added to it, since
+/// seldom_state keeps its transitions private
+pub struct GraphedStateMachine {
+    machine: StateMachine,
+    graph: StateGraph,
+}
+
+impl GraphedStateMachine {
+    pub fn new<Initial: Component>() -> Self {
+        Self {
+            machine: StateMachine::default(),
+            graph: StateGraph {
+                initial: short_type_name(type_name::<Initial>()),
+                edges: Vec::new(),
+            },
+        }
+    }
+
+    pub fn trans<S: MachineState>(
+        mut self,
+        trigger: impl Trigger,
+        state: impl Clone + Component,
+    ) -> Self {
+        self.graph.push(
+            type_name::<S>(),
+            trigger_name(&trigger),
+            type_name_of_val(&state),
+        );
+        self.machine = self.machine.trans::<S>(trigger, state);
+        self
+    }
+
+    /// Same as [`Self::trans`], but labels the edge with `label` instead of the trigger's type name
+    pub fn trans_labeled<S: MachineState>(
+        mut self,
+        trigger: impl Trigger,
+        label: String,
+        state: impl Clone + Component,
+    ) -> Self {
+        self.graph
+            .push(type_name::<S>(), label, type_name_of_val(&state));
+        self.machine = self.machine.trans::<S>(trigger, state);
+        self
+    }
+
+    pub fn trans_builder<Prev: MachineState, Trig: Trigger, Next: Clone + Component>(
+        mut self,
+        trigger: Trig,
+        builder: impl 'static + Clone + Fn(&Prev, Trig::Ok) -> Option<Next> + Send + Sync,
+    ) -> Self {
+        self.graph.push(
+            type_name::<Prev>(),
+            trigger_name(&trigger),
+            type_name::<Next>(),
+        );
+        self.machine = self.machine.trans_builder(trigger, builder);
+        self
+    }
+
+    pub fn build(self) -> (StateMachine, StateGraph) {
+        (self.machine, self.graph)
+    }
+}
+
+fn trigger_name<T: Trigger>(_: &T) -> String {
+    short_type_name(type_name::<T>())
+}
+
+fn type_name_of_val<T>(_: &T) -> &'static str {
+    type_name::<T>()
+}
+
+/// Strips the module paths from a type name, including inside generics
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment = String::new();
+
+    for c in name.chars() {
+        match c {
+            ':' => segment.clear(),
+            '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | '&' => {
+                short.push_str(&segment);
+                segment.clear();
+                short.push(c);
+            }
+            _ => segment.push(c),
+        }
+    }
+    short.push_str(&segment);
+
+    short
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateEdge {
+    pub from: String,
+    pub trigger: String,
+    pub to: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StateGraph {
+    pub initial: String,
+    pub edges: Vec<StateEdge>,
+}
+
+impl StateGraph {
+    fn push(&mut self, from: &str, trigger: String, to: &str) {
+        self.edges.push(StateEdge {
+            from: short_type_name(from),
+            trigger,
+            to: short_type_name(to),
+        });
+    }
+
+    /// Every state mentioned by the graph, in the order they first appear
+    pub fn states(&self) -> Vec<&str> {
+        let mut states = vec![self.initial.as_str()];
+        for edge in self.edges.iter() {
+            for state in [edge.from.as_str(), edge.to.as_str()] {
+                if !states.contains(&state) {
+                    states.push(state);
+                }
+            }
+        }
+        states
+    }
+
+    /// States that can never be entered when starting from the initial state. Edges from
+    /// [`AnyState`] can be taken from every reached state
+    pub fn unreachable_states(&self) -> Vec<&str> {
+        let any_state = short_type_name(type_name::<AnyState>());
+        let mut reached = HashSet::from([self.initial.as_str()]);
+        let mut open = vec![self.initial.as_str()];
+
+        while let Some(state) = open.pop() {
+            for edge in self
+                .edges
+                .iter()
+                .filter(|edge| edge.from == state || edge.from == any_state)
+            {
+                if reached.insert(edge.to.as_str()) {
+                    open.push(edge.to.as_str());
+                }
+            }
+        }
+
+        self.states()
+            .into_iter()
+            .filter(|state| *state != any_state && !reached.contains(state))
+            .collect()
+    }
+
+    pub fn to_dot(&self, name: &str) -> String {
+        let unreachable = self.unreachable_states();
+        let mut dot = String::new();
+
+        // Writing to a String can't fail
+        let _ = writeln!(dot, "digraph {name} {{");
+        let _ = writeln!(dot, "    rankdir=LR;");
+        let _ = writeln!(dot, "    node [shape=box, style=rounded];");
+        for state in self.states() {
+            let mut attributes = Vec::new();
+            if state == self.initial {
+                attributes.push("peripheries=2");
+            }
+            if unreachable.contains(&state) {
+                attributes.push("color=red, fontcolor=red");
+            }
+            let _ = writeln!(dot, "    \"{state}\" [{}];", attributes.join(", "));
+        }
+        for edge in self.edges.iter() {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                edge.from,
+                edge.to,
+                edge.trigger.replace('"', "\\\"")
+            );
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::state_machine::{states::*, triggers::*};
+
+    /// Jumps from the ground, but never falls, so falling and landing can't be reached
+    fn graph() -> StateGraph {
+        let (_, graph) = GraphedStateMachine::new::<GroundedState>()
+            .trans::<GroundedState>(JumpTrigger, JumpingState(1f32))
+            .trans::<FallingState>(GroundedTrigger, GroundedState::Idle)
+            .build();
+        graph
+    }
+
+    #[test]
+    fn short_type_name_strips_nested_paths() {
+        assert_eq!(
+            short_type_name("core::option::Option<alloc::vec::Vec<platformer::State>>"),
+            "Option<Vec<State>>"
+        );
+        assert_eq!(
+            short_type_name("(a::B, &[c::D<e::F, g::H>])"),
+            "(B, &[D<F, H>])"
+        );
+        assert_eq!(
+            short_type_name(type_name::<Option<JumpTrigger>>()),
+            "Option<JumpTrigger>"
+        );
+    }
+
+    #[test]
+    fn unreachable_states_are_found() {
+        let graph = graph();
+
+        assert_eq!(
+            graph.states(),
+            vec!["GroundedState", "JumpingState", "FallingState"]
+        );
+        assert_eq!(graph.unreachable_states(), vec!["FallingState"]);
+    }
+
+    #[test]
+    fn any_state_edges_leave_every_state() {
+        let (_, graph) = GraphedStateMachine::new::<GroundedState>()
+            .trans::<AnyState>(JumpTrigger, FallingState)
+            .trans::<DeadState>(GroundedTrigger, GroundedState::Idle)
+            .build();
+
+        // AnyState is never entered itself, so it isn't reported
+        assert_eq!(graph.unreachable_states(), vec!["DeadState"]);
+    }
+
+    #[test]
+    fn dot_labels_edges_with_triggers() {
+        let dot = graph().to_dot("Player");
+
+        assert!(dot.starts_with("digraph Player {\n"), "{dot}");
+        assert!(dot.contains("\"GroundedState\" [peripheries=2];"), "{dot}");
+        assert!(
+            dot.contains("\"FallingState\" [color=red, fontcolor=red];"),
+            "{dot}"
+        );
+        assert!(
+            dot.contains("\"GroundedState\" -> \"JumpingState\" [label=\"JumpTrigger\"];"),
+            "{dot}"
+        );
+        assert!(
+            dot.contains("\"FallingState\" -> \"GroundedState\" [label=\"GroundedTrigger\"];"),
+            "{dot}"
+        );
+        assert!(dot.ends_with("}\n"), "{dot}");
+    }
+}
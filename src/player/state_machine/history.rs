@@ -0,0 +1,233 @@
+use bevy::{core::FrameCount, prelude::*};
+use seldom_state::set::StateSet;
+use std::collections::VecDeque;
+
+use super::states::*;
+
+pub(super) struct StateHistoryPlugin;
+
+impl Plugin for StateHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerStateChanged>()
+            .add_systems(PostUpdate, record_state_changes.after(StateSet::Transition))
+            .register_type::<PlayerState>();
+    }
+}
+
+pub type PlayerStateComponents<'a> = (
+    Option<&'a GroundedState>,
+    Option<&'a JumpingState>,
+    Option<&'a FallingState>,
+    Option<&'a DeadState>,
+);
+
+/// Flat view of whichever state component the player's state machine currently has inserted
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum PlayerState {
+    Grounded(GroundedState),
+    Jumping(f32),
+    Falling,
+    Dead,
+}
+
+impl PlayerState {
+    pub fn from_components(components: PlayerStateComponents) -> Option<Self> {
+        match components {
+            (_, _, _, Some(_)) => Some(PlayerState::Dead),
+            (Some(state), _, _, _) => Some(PlayerState::Grounded(*state)),
+            (_, Some(state), _, _) => Some(PlayerState::Jumping(state.0)),
+            (_, _, Some(_), _) => Some(PlayerState::Falling),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerStateChanged {
+    pub entity: Entity,
+    pub from: PlayerState,
+    pub to: PlayerState,
+    pub tick: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StateHistoryEntry {
+    pub from: PlayerState,
+    pub to: PlayerState,
+    pub tick: u32,
+    pub time: f32,
+}
+
+/// Ring buffer of the last `capacity` transitions of a state machine, oldest first
+#[derive(Component, Clone, Debug)]
+pub struct StateHistory {
+    capacity: usize,
+    entries: VecDeque<StateHistoryEntry>,
+    current: Option<PlayerState>,
+}
+
+impl StateHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+            current: None,
+        }
+    }
+
+    /// Records a transition, dropping the oldest one when full
+    pub fn push(&mut self, entry: StateHistoryEntry) {
+        self.current = Some(entry.to);
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// State the last transition went to, or the initial state if there was none
+    pub fn current(&self) -> Option<PlayerState> {
+        self.current
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &StateHistoryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn last(&self) -> Option<&StateHistoryEntry> {
+        self.entries.back()
+    }
+}
+
+impl Default for StateHistory {
+    fn default() -> Self {
+        Self::new(32)
+    }
+}
+
+/// Compares the player's state with the last recorded one every frame, so transitions that end
+/// in the state they started from aren't recorded. The walking transition re-enters the grounded
+/// state every frame, so recording each transition the state machine takes would bury the rest
+pub(crate) fn record_state_changes(
+    mut player_query: Query<(Entity, &mut StateHistory, PlayerStateComponents)>,
+    mut state_changed: EventWriter<PlayerStateChanged>,
+    frames: Res<FrameCount>,
+    time: Res<Time>,
+) {
+    for (entity, mut history, state) in player_query.iter_mut() {
+        let Some(to) = PlayerState::from_components(state) else {
+            continue;
+        };
+
+        let from = match history.current {
+            Some(from) if from != to => from,
+            Some(_) => continue,
+            None => {
+                // The initial state is not a transition
+                history.current = Some(to);
+                continue;
+            }
+        };
+
+        history.push(StateHistoryEntry {
+            from,
+            to,
+            tick: frames.0,
+            time: time.elapsed_seconds(),
+        });
+        state_changed.send(PlayerStateChanged {
+            entity,
+            from,
+            to,
+            tick: frames.0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(from: PlayerState, to: PlayerState, tick: u32) -> StateHistoryEntry {
+        StateHistoryEntry {
+            from,
+            to,
+            tick,
+            time: 0f32,
+        }
+    }
+
+    fn ticks(history: &StateHistory) -> Vec<u32> {
+        history.entries().map(|entry| entry.tick).collect()
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted() {
+        let mut history = StateHistory::new(3);
+        for tick in 0..5 {
+            history.push(entry(PlayerState::Falling, PlayerState::Dead, tick));
+        }
+
+        assert_eq!(ticks(&history), vec![2, 3, 4]);
+        assert_eq!(history.last().map(|entry| entry.tick), Some(4));
+    }
+
+    #[test]
+    fn current_follows_the_last_entry() {
+        let mut history = StateHistory::new(2);
+        assert_eq!(history.current(), None);
+
+        history.push(entry(PlayerState::Falling, PlayerState::Jumping(1f32), 0));
+        history.push(entry(PlayerState::Jumping(1f32), PlayerState::Dead, 1));
+
+        assert_eq!(history.current(), Some(PlayerState::Dead));
+    }
+
+    #[test]
+    fn zero_capacity_keeps_only_the_current_state() {
+        let mut history = StateHistory::new(0);
+        history.push(entry(PlayerState::Falling, PlayerState::Dead, 0));
+
+        assert_eq!(ticks(&history), Vec::<u32>::new());
+        assert_eq!(history.current(), Some(PlayerState::Dead));
+    }
+
+    #[test]
+    fn changes_of_state_are_recorded() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StateHistoryPlugin));
+        let player = app
+            .world
+            .spawn((StateHistory::default(), FallingState))
+            .id();
+
+        app.update();
+        let history = app.world.get::<StateHistory>(player).unwrap();
+        assert_eq!(history.current(), Some(PlayerState::Falling));
+        assert!(history.last().is_none());
+
+        app.world
+            .entity_mut(player)
+            .remove::<FallingState>()
+            .insert(GroundedState::Idle);
+        app.update();
+        // Re-entering the same state isn't a change
+        app.world.entity_mut(player).insert(GroundedState::Idle);
+        app.update();
+
+        let history = app.world.get::<StateHistory>(player).unwrap();
+        let transitions = history
+            .entries()
+            .map(|entry| (entry.from, entry.to))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            transitions,
+            vec![(
+                PlayerState::Falling,
+                PlayerState::Grounded(GroundedState::Idle)
+            )]
+        );
+    }
+}
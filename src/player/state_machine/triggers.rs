@@ -4,24 +4,38 @@
 use bevy::prelude::*;
 use seldom_state::prelude::*;
 
+use super::states::DeadState;
 use crate::player::{
+    health::Health,
     movement::{sub_components::Surface, CharacterController},
     Player,
 };
 
+/// A trigger that only depends on the state of a [`CharacterController`]. These can be named in
+/// state machine definition files through the [`TriggerRegistry`](super::definition::TriggerRegistry)
+pub trait ControllerTrigger: 'static + Send + Sync {
+    fn check(&self, controller: &CharacterController) -> bool;
+}
+
 #[derive(Debug)]
 pub struct JumpTrigger;
 
+impl ControllerTrigger for JumpTrigger {
+    fn check(&self, controller: &CharacterController) -> bool {
+        !controller.coyote_timer.finished() && !controller.jump_buffer_timer.finished()
+    }
+}
+
 impl BoolTrigger for JumpTrigger {
     type Param<'w, 's> = Query<'w, 's, &'static CharacterController, With<Player>>;
 
     fn trigger(&self, _: Entity, param: Self::Param<'_, '_>) -> bool {
         match param.get_single() {
-            Ok(val) => !val.coyote_timer.finished() && !val.jump_buffer_timer.finished(),
+            Ok(val) => self.check(val),
             Err(message) => {
                 println!(
                     "Could not get controller and velocity in jump trigger. Error message: {}",
-                    message.to_string()
+                    message
                 );
                 false
             }
@@ -32,16 +46,22 @@ impl BoolTrigger for JumpTrigger {
 #[derive(Debug)]
 pub struct GroundedTrigger;
 
+impl ControllerTrigger for GroundedTrigger {
+    fn check(&self, controller: &CharacterController) -> bool {
+        controller
+            .surface_checker
+            .surface_touching_ground(&Surface::Bottom)
+    }
+}
+
 impl BoolTrigger for GroundedTrigger {
     type Param<'w, 's> = Query<'w, 's, &'static CharacterController, With<Player>>;
 
     fn trigger(&self, _: Entity, param: Self::Param<'_, '_>) -> bool {
         match param.get_single() {
-            Ok(val) => val
-                .surface_checker
-                .surface_touching_ground(&Surface::Bottom),
+            Ok(val) => self.check(val),
             Err(message) => {
-                println!("Could not get player character controller in grounded trigger. Error message: {}", message.to_string());
+                println!("Could not get player character controller in grounded trigger. Error message: {}", message);
                 false
             }
         }
@@ -51,20 +71,28 @@ impl BoolTrigger for GroundedTrigger {
 #[derive(Debug)]
 pub struct WallslidingTrigger;
 
+impl ControllerTrigger for WallslidingTrigger {
+    fn check(&self, controller: &CharacterController) -> bool {
+        (controller
+            .surface_checker
+            .surface_touching_ground(&Surface::Left)
+            || controller
+                .surface_checker
+                .surface_touching_ground(&Surface::Right))
+            && !controller
+                .surface_checker
+                .surface_touching_ground(&Surface::Bottom)
+    }
+}
+
 impl BoolTrigger for WallslidingTrigger {
     type Param<'w, 's> = Query<'w, 's, &'static CharacterController, With<Player>>;
 
     fn trigger(&self, _: Entity, param: Self::Param<'_, '_>) -> bool {
         match param.get_single() {
-            Ok(val) => {
-                (val.surface_checker.surface_touching_ground(&Surface::Left)
-                    || val.surface_checker.surface_touching_ground(&Surface::Right))
-                    && !val
-                        .surface_checker
-                        .surface_touching_ground(&Surface::Bottom)
-            }
+            Ok(val) => self.check(val),
             Err(message) => {
-                println!("Could not get player character controller in grounded trigger. Error message: {}", message.to_string());
+                println!("Could not get player character controller in grounded trigger. Error message: {}", message);
                 false
             }
         }
@@ -74,18 +102,49 @@ impl BoolTrigger for WallslidingTrigger {
 #[derive(Debug)]
 pub struct FallingTrigger;
 
+impl ControllerTrigger for FallingTrigger {
+    fn check(&self, controller: &CharacterController) -> bool {
+        !controller
+            .surface_checker
+            .surface_touching_ground(&Surface::Bottom)
+    }
+}
+
 impl BoolTrigger for FallingTrigger {
     type Param<'w, 's> = Query<'w, 's, &'static CharacterController, With<Player>>;
 
     fn trigger(&self, _: Entity, param: Self::Param<'_, '_>) -> bool {
         match param.get_single() {
-            Ok(val) => !val
-                .surface_checker
-                .surface_touching_ground(&Surface::Bottom),
+            Ok(val) => self.check(val),
             Err(message) => {
-                println!("Could not get player character controller in falling trigger. Error message: {}", message.to_string());
+                println!("Could not get player character controller in falling trigger. Error message: {}", message);
                 false
             }
         }
     }
 }
+
+/// Fires with the bounce multiplier of the ground the character is standing on, if it is bouncy
+#[derive(Debug)]
+pub struct BounceTrigger;
+
+impl OptionTrigger for BounceTrigger {
+    type Param<'w, 's> = Query<'w, 's, &'static CharacterController>;
+    type Some = f32;
+
+    fn trigger(&self, entity: Entity, param: Self::Param<'_, '_>) -> Option<f32> {
+        param.get(entity).ok()?.ground_material().bounce
+    }
+}
+
+/// Fires when the character has run out of health and isn't already dead
+#[derive(Debug)]
+pub struct DeadTrigger;
+
+impl BoolTrigger for DeadTrigger {
+    type Param<'w, 's> = Query<'w, 's, &'static Health, Without<DeadState>>;
+
+    fn trigger(&self, entity: Entity, param: Self::Param<'_, '_>) -> bool {
+        param.get(entity).is_ok_and(Health::is_dead)
+    }
+}
@@ -1,21 +1,347 @@
-use super::{Player, PlayerStartupSet};
+use super::{
+    facing::Facing,
+    health::Health,
+    movement::CharacterController,
+    state_machine::states::{DeadState, GroundedState, JumpingState},
+    Player, PlayerSet, PlayerStartupSet,
+};
 use bevy::{prelude::*, render::texture::DEFAULT_IMAGE_HANDLE};
+use bevy_rapier2d::prelude::*;
 
 pub(super) struct PlayerVisualsPlugin;
 
 impl Plugin for PlayerVisualsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, init.in_set(PlayerStartupSet::Visuals));
+        app.add_systems(Startup, init.in_set(PlayerStartupSet::Visuals))
+            // Presets can resize the player in menus too
+            .add_systems(Update, size_visual.before(PlayerSet::Visuals))
+            .add_systems(
+                Update,
+                (
+                    track_fall_speed,
+                    stretch_on_jump,
+                    squash_on_land,
+                    relax_squash_stretch,
+                    flip_sprite,
+                    fade_on_damage,
+                )
+                    .chain()
+                    .in_set(PlayerSet::Visuals),
+            )
+            .register_type::<PlayerVisual>()
+            .register_type::<SquashStretch>();
+    }
+}
+
+/// Child entity holding the player's sprite, placed relative to the physics body on the parent.
+/// The sprite is as big as the [`CharacterController`]'s body, and nothing done to this entity can
+/// affect collision
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct PlayerVisual {
+    pub offset: Vec2,
+    pub scale: Vec2,
+}
+
+impl Default for PlayerVisual {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            scale: Vec2::ONE,
+        }
     }
 }
 
 pub fn init(mut cmd: Commands, player_query: Query<Entity, With<Player>>) {
-    cmd.entity(player_query.single()).insert((
-        Sprite {
-            custom_size: Some((25f32, 50f32).into()),
-            color: Color::rgb_u8(125, 205, 255),
-            ..Default::default()
-        },
-        Handle::<Image>::from(DEFAULT_IMAGE_HANDLE.typed()),
-    ));
+    let player = player_query.single();
+
+    let visual = cmd
+        .spawn((
+            SpriteBundle {
+                // Sized by `size_visual` once the controller is added
+                sprite: Sprite {
+                    color: Color::rgb_u8(125, 205, 255),
+                    ..Default::default()
+                },
+                texture: DEFAULT_IMAGE_HANDLE.typed(),
+                ..Default::default()
+            },
+            PlayerVisual::default(),
+            Name::from("Player visual"),
+        ))
+        .id();
+
+    cmd.entity(player)
+        .insert(SquashStretch::default())
+        .add_child(visual);
+}
+
+/// Keeps the sprite as big as the body, which presets and the tuning panel can resize
+fn size_visual(
+    player_query: Query<(&CharacterController, &Children), Changed<CharacterController>>,
+    mut visual_query: Query<&mut Sprite, With<PlayerVisual>>,
+) {
+    for (controller, children) in player_query.iter() {
+        let mut sprites = visual_query.iter_many_mut(children);
+        while let Some(mut sprite) = sprites.fetch_next() {
+            if sprite.custom_size != Some(controller.size) {
+                sprite.custom_size = Some(controller.size);
+            }
+        }
+    }
+}
+
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct SquashStretch {
+    pub jump_stretch: Vec2,
+    pub land_squash: Vec2,
+    pub min_impact_speed: f32,
+    pub full_squash_speed: f32,
+    pub recovery_speed: f32,
+
+    pub scale: Vec2,
+    pub fall_speed: f32,
+}
+
+impl Default for SquashStretch {
+    fn default() -> Self {
+        Self {
+            jump_stretch: Vec2::new(0.75f32, 1.3f32),
+            land_squash: Vec2::new(1.35f32, 0.65f32),
+            min_impact_speed: 150f32,
+            full_squash_speed: 900f32,
+            recovery_speed: 12f32,
+
+            scale: Vec2::ONE,
+            fall_speed: 0f32,
+        }
+    }
+}
+
+fn track_fall_speed(
+    mut player_query: Query<(&mut SquashStretch, &Velocity), Without<GroundedState>>,
+) {
+    for (mut squash, vel) in player_query.iter_mut() {
+        squash.fall_speed = (-vel.linvel.y).max(0f32);
+    }
+}
+
+fn stretch_on_jump(
+    mut player_query: Query<&mut SquashStretch, (With<Player>, Added<JumpingState>)>,
+) {
+    for mut squash in player_query.iter_mut() {
+        squash.scale = squash.jump_stretch;
+    }
+}
+
+fn squash_on_land(
+    mut player_query: Query<&mut SquashStretch, (With<Player>, Added<GroundedState>)>,
+) {
+    for mut squash in player_query.iter_mut() {
+        // GroundedState is re-inserted every frame by the walking transition, so the stored fall
+        // speed is consumed here to only squash on the actual landing
+        let impact = std::mem::take(&mut squash.fall_speed);
+        if impact < squash.min_impact_speed {
+            continue;
+        }
+
+        let t = ((impact - squash.min_impact_speed)
+            / (squash.full_squash_speed - squash.min_impact_speed).max(f32::EPSILON))
+        .clamp(0f32, 1f32);
+        squash.scale = Vec2::ONE.lerp(squash.land_squash, t);
+    }
+}
+
+fn relax_squash_stretch(
+    mut player_query: Query<(&mut SquashStretch, &Children), With<Player>>,
+    mut visual_query: Query<(&mut Transform, &Sprite, &PlayerVisual)>,
+    time: Res<Time>,
+) {
+    for (mut squash, children) in player_query.iter_mut() {
+        let t = 1f32 - (-squash.recovery_speed * time.delta_seconds()).exp();
+        squash.scale = squash.scale.lerp(Vec2::ONE, t);
+
+        for child in children.iter() {
+            let Ok((mut transform, sprite, visual)) = visual_query.get_mut(*child) else {
+                continue;
+            };
+
+            // Keep the feet planted while the sprite is squashed
+            let height = sprite.custom_size.map_or(0f32, |size| size.y) * visual.scale.y;
+            let anchor = Vec2::Y * (squash.scale.y - 1f32) * height / 2f32;
+
+            transform.scale = (visual.scale * squash.scale).extend(1f32);
+            transform.translation = (visual.offset + anchor).extend(transform.translation.z);
+        }
+    }
+}
+
+fn flip_sprite(
+    player_query: Query<(&Facing, &Children), Changed<Facing>>,
+    mut visual_query: Query<&mut Sprite, With<PlayerVisual>>,
+) {
+    for (facing, children) in player_query.iter() {
+        let mut sprites = visual_query.iter_many_mut(children);
+        while let Some(mut sprite) = sprites.fetch_next() {
+            sprite.flip_x = *facing == Facing::Left;
+        }
+    }
+}
+
+/// Blinks the sprite while the player is invulnerable and fades it out while dying
+fn fade_on_damage(
+    player_query: Query<(&Health, Option<&DeadState>, &Children)>,
+    mut visual_query: Query<&mut Sprite, With<PlayerVisual>>,
+) {
+    for (health, dead, children) in player_query.iter() {
+        let alpha = if dead.is_some() {
+            1f32 - health.death.percent()
+        } else if health.invulnerable() {
+            // Blink ten times a second
+            if ((health.invulnerability.elapsed_secs() * 10f32) as u32).is_multiple_of(2) {
+                0.3f32
+            } else {
+                1f32
+            }
+        } else {
+            1f32
+        };
+
+        let mut sprites = visual_query.iter_many_mut(children);
+        while let Some(mut sprite) = sprites.fetch_next() {
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::movement::CharacterControllerBuilder;
+
+    const HALF_SIZE: Vec2 = Vec2::new(12.5f32, 25f32);
+
+    /// A player with a visual child, running the squash and stretch systems. Time doesn't pass,
+    /// so the squash and stretch never relax
+    fn app() -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>().add_systems(
+            Update,
+            (
+                track_fall_speed,
+                stretch_on_jump,
+                squash_on_land,
+                relax_squash_stretch,
+            )
+                .chain(),
+        );
+
+        let visual = app
+            .world
+            .spawn((
+                Sprite {
+                    custom_size: Some(HALF_SIZE * 2f32),
+                    ..Default::default()
+                },
+                Transform::default(),
+                PlayerVisual::default(),
+            ))
+            .id();
+        let player = app
+            .world
+            .spawn((
+                Player,
+                SquashStretch::default(),
+                Velocity::default(),
+                Collider::cuboid(HALF_SIZE.x, HALF_SIZE.y),
+                Transform::default(),
+            ))
+            .id();
+        app.world.entity_mut(player).push_children(&[visual]);
+        app.update();
+
+        (app, player, visual)
+    }
+
+    fn visual_scale(app: &App, visual: Entity) -> Vec2 {
+        app.world.get::<Transform>(visual).unwrap().scale.truncate()
+    }
+
+    fn assert_collider_unscaled(app: &App, player: Entity) {
+        assert_eq!(app.world.get::<Transform>(player).unwrap().scale, Vec3::ONE);
+
+        let collider = app.world.get::<Collider>(player).unwrap();
+        assert_eq!(collider.scale(), Vec2::ONE);
+        assert_eq!(collider.as_cuboid().unwrap().half_extents(), HALF_SIZE);
+    }
+
+    #[test]
+    fn jump_stretches_visual_only() {
+        let (mut app, player, visual) = app();
+        assert_eq!(visual_scale(&app, visual), Vec2::ONE);
+
+        app.world.entity_mut(player).insert(JumpingState(0f32));
+        app.update();
+
+        assert_eq!(
+            visual_scale(&app, visual),
+            SquashStretch::default().jump_stretch
+        );
+        assert_collider_unscaled(&app, player);
+    }
+
+    #[test]
+    fn landing_squashes_visual_only() {
+        let (mut app, player, visual) = app();
+        let squash = SquashStretch::default();
+
+        app.world.get_mut::<Velocity>(player).unwrap().linvel.y = -squash.full_squash_speed;
+        app.update();
+        app.world.entity_mut(player).insert(GroundedState::Idle);
+        app.update();
+
+        assert_eq!(visual_scale(&app, visual), squash.land_squash);
+        // The feet stay where they were
+        let translation = app.world.get::<Transform>(visual).unwrap().translation;
+        assert!(translation.y < 0f32);
+        assert_collider_unscaled(&app, player);
+    }
+
+    #[test]
+    fn soft_landing_doesnt_squash() {
+        let (mut app, player, visual) = app();
+        let squash = SquashStretch::default();
+
+        app.world.get_mut::<Velocity>(player).unwrap().linvel.y = -squash.min_impact_speed / 2f32;
+        app.update();
+        app.world.entity_mut(player).insert(GroundedState::Idle);
+        app.update();
+
+        assert_eq!(visual_scale(&app, visual), Vec2::ONE);
+    }
+
+    #[test]
+    fn visual_follows_the_body_size() {
+        let mut app = App::new();
+        app.add_systems(Update, size_visual);
+        let visual = app
+            .world
+            .spawn((Sprite::default(), PlayerVisual::default()))
+            .id();
+        let player = app
+            .world
+            .spawn(CharacterControllerBuilder::default().build())
+            .push_children(&[visual])
+            .id();
+        let size = |app: &App| app.world.get::<Sprite>(visual).unwrap().custom_size;
+
+        app.update();
+        assert_eq!(size(&app), Some(CharacterControllerBuilder::default().size));
+
+        app.world
+            .get_mut::<CharacterController>(player)
+            .unwrap()
+            .size = Vec2::new(30f32, 40f32);
+        app.update();
+        assert_eq!(size(&app), Some(Vec2::new(30f32, 40f32)));
+    }
 }